@@ -10,16 +10,6 @@ pub struct Aabb {
     z: Range<f64>,
 }
 
-#[inline]
-const fn axis_for_vec(vec: &Vec3d, n: usize) -> f64 {
-    match n {
-        0 => vec.x,
-        1 => vec.y,
-        2 => vec.z,
-        _ => panic!("Axis is not supported"),
-    }
-}
-
 fn merge(a: &Range<f64>, b: &Range<f64>) -> Range<f64> {
     Range::<f64> {
         start: a.start.min(b.start),
@@ -46,6 +36,39 @@ impl Aabb {
         }
     }
 
+    /// Expand axes which are thinner than `delta`, to avoid a degenerate (flat) box
+    pub fn pad(self, delta: f64) -> Self {
+        let pad_axis = |axis: Range<f64>| {
+            if axis.end - axis.start < delta {
+                (axis.start - delta / 2.0)..(axis.end + delta / 2.0)
+            } else {
+                axis
+            }
+        };
+
+        Self {
+            x: pad_axis(self.x),
+            y: pad_axis(self.y),
+            z: pad_axis(self.z),
+        }
+    }
+
+    pub fn centroid(&self) -> Vec3d {
+        Vec3d {
+            x: (self.x.start + self.x.end) / 2.0,
+            y: (self.y.start + self.y.end) / 2.0,
+            z: (self.z.start + self.z.end) / 2.0,
+        }
+    }
+
+    pub fn surface_area(&self) -> f64 {
+        let dx = self.x.end - self.x.start;
+        let dy = self.y.end - self.y.start;
+        let dz = self.z.end - self.z.start;
+
+        2.0 * (dx * dy + dy * dz + dz * dx)
+    }
+
     #[inline]
     pub const fn axis(&self, n: usize) -> &Range<f64> {
         match n {
@@ -56,10 +79,19 @@ impl Aabb {
         }
     }
 
-    pub fn hit(&self, ray: &Ray, mut interval: Range<f64>) -> bool {
+    /// Test if `ray` passes through the box within `interval`
+    ///
+    /// `inv_direction` is `1.0 / ray.direction`, computed once per ray by the caller.
+    #[inline]
+    pub fn hit(&self, ray: &Ray, inv_direction: &Vec3d, interval: Range<f64>) -> bool {
+        self.hit_interval(ray, inv_direction, interval).is_some()
+    }
+
+    /// Part of `interval` in which `ray` is inside the box, if any
+    pub fn hit_interval(&self, ray: &Ray, inv_direction: &Vec3d, mut interval: Range<f64>) -> Option<Range<f64>> {
         for axis in 0..3 {
-            let inv_d = axis_for_vec(&(1.0 / ray.direction), axis);
-            let orig = axis_for_vec(&ray.origin, axis);
+            let inv_d = inv_direction.axis(axis);
+            let orig = ray.origin.axis(axis);
 
             let mut t0 = (self.axis(axis).start - orig) * inv_d;
             let mut t1 = (self.axis(axis).end - orig) * inv_d;
@@ -76,10 +108,10 @@ impl Aabb {
             };
 
             if interval.end <= interval.start {
-                return false;
+                return None;
             }
         }
 
-        true
+        Some(interval)
     }
 }
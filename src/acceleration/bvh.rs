@@ -0,0 +1,744 @@
+use std::{borrow::Cow, path::Path};
+
+use rayon::prelude::*;
+
+use super::{bvh_cache, stats, Aabb};
+use crate::core::{HitRecord, Hittable, Ray, Vec3d};
+
+/// Number of buckets the centroid range is divided into when looking for a split plane
+const SAH_BINS: usize = 16;
+
+/// Leaves never hold more primitives, even if splitting them looks more expensive
+const MAX_LEAF_SIZE: usize = 4;
+
+/// Cost of visiting a node, relative to intersecting a single primitive
+const TRAVERSAL_COST: f64 = 0.5;
+
+/// Below this depth nodes are split at the median, which bounds the depth of the tree to fit the
+/// traversal stack
+const MAX_SAH_DEPTH: usize = 32;
+
+/// Maximum depth of the tree, as nodes still to visit are kept on a fixed size stack
+pub(super) const STACK_SIZE: usize = 64;
+
+/// Rebuild the BVH of an animation once refitting made it this much more expensive to traverse
+pub const MAX_BVH_DEGRADATION: f64 = 2.0;
+
+/// Subtrees with fewer primitives on either side are built on the current thread
+const MIN_PARALLEL_BUILD: usize = 1024;
+
+/// Bounding volume hierarchy over a list of primitives
+///
+/// All nodes are stored depth first in a single `Vec`: the first child of an interior node directly
+/// follows it, the second child is referenced by its index. The primitives are reordered during the
+/// build, so each leaf references a contiguous range of them.
+#[derive(Clone)]
+pub struct Bvh<T> {
+    nodes: Vec<LinearNode>,
+    primitives: Vec<T>,
+
+    /// SAH cost right after the build, to tell how much refitting degraded the tree
+    build_cost: f64,
+}
+
+#[derive(Clone)]
+pub(super) struct LinearNode {
+    pub(super) bounding_box: Aabb,
+    /// Index of the first primitive for leaves, index of the second child for interior nodes
+    pub(super) offset: u32,
+    /// Number of primitives, 0 for interior nodes
+    pub(super) count: u16,
+    /// Split axis of interior nodes, decides which child is visited first
+    pub(super) axis: u8,
+}
+
+/// Shape of a built hierarchy, to compare the quality of trees
+pub struct BvhStats {
+    pub nodes: usize,
+    pub leaves: usize,
+    pub max_depth: usize,
+
+    /// Average depth of the leaves
+    pub avg_depth: f64,
+
+    pub min_leaf_size: usize,
+    pub max_leaf_size: usize,
+    pub avg_leaf_size: f64,
+
+    /// Expected cost of tracing a ray, relative to intersecting one primitive
+    pub sah_cost: f64,
+}
+
+/// Primitive while building the hierarchy
+struct BuildItem {
+    index: usize,
+    bounding_box: Aabb,
+    centroid: Vec3d,
+}
+
+/// Node of the temporary tree, which is flattened once all subtrees are built
+enum BuildNode {
+    Leaf {
+        bounding_box: Aabb,
+        offset: usize,
+        count: usize,
+    },
+    Interior {
+        bounding_box: Aabb,
+        axis: usize,
+        children: Box<[BuildNode; 2]>,
+    },
+}
+
+impl<T: Hittable + Sync> Bvh<T> {
+    /// Build a bounding volume hierarchy over `primitives`
+    ///
+    /// Each node is split with the binned surface area heuristic, so the same input always produces
+    /// the same tree. Large subtrees are built in parallel.
+    pub fn build(primitives: Vec<T>) -> Self {
+        Self::build_cached(primitives, None)
+    }
+
+    /// Like `build`, but reuses the tree saved in `cache_dir` by an earlier run for primitives with
+    /// the same bounding boxes, or saves the new tree there
+    pub fn build_cached(primitives: Vec<T>, cache_dir: Option<&Path>) -> Self {
+        if primitives.is_empty() {
+            panic!("Can't build a bounding volume hierarchy without any objects");
+        }
+
+        let boxes: Vec<_> = primitives
+            .par_iter()
+            .map(|primitive| primitive.bounding_box().into_owned())
+            .collect();
+
+        let cache = cache_dir.map(|dir| (dir, bvh_cache::geometry_hash(&boxes)));
+        let cached = cache.and_then(|(dir, hash)| bvh_cache::read(dir, hash, boxes.len()));
+
+        let (nodes, order) = cached.unwrap_or_else(|| {
+            let (nodes, order) = build_tree(boxes);
+            if let Some((dir, hash)) = cache {
+                bvh_cache::write(dir, hash, &nodes, &order);
+            }
+            (nodes, order)
+        });
+
+        // Store the primitives in leaf order
+        let mut slots: Vec<_> = primitives.into_iter().map(Some).collect();
+        let primitives = order
+            .iter()
+            .map(|&index| slots[index as usize].take().unwrap())
+            .collect();
+
+        let mut bvh = Bvh {
+            nodes,
+            primitives,
+            build_cost: 0.0,
+        };
+        bvh.build_cost = bvh.sah_cost();
+        bvh
+    }
+
+    /// The primitives in leaf order
+    pub fn primitives(&self) -> &[T] {
+        &self.primitives
+    }
+
+    /// The primitives in leaf order, to move them before calling `refit`
+    pub fn primitives_mut(&mut self) -> &mut [T] {
+        &mut self.primitives
+    }
+
+    /// Update all bounding boxes after the primitives moved, keeping the structure of the tree
+    ///
+    /// If the SAH cost grew by more than the factor `max_degradation` since the last build, the
+    /// tree is built again from scratch. Returns true if it was rebuilt.
+    pub fn refit(&mut self, max_degradation: Option<f64>) -> bool {
+        // Children always come after their parent, so walking backwards visits them first
+        for index in (0..self.nodes.len()).rev() {
+            let node = &self.nodes[index];
+            let bounding_box = if node.count > 0 {
+                let start = node.offset as usize;
+                self.primitives[start..start + node.count as usize]
+                    .iter()
+                    .fold(None, |acc, primitive| Some(union(acc, &primitive.bounding_box())))
+                    .unwrap()
+            } else {
+                Aabb::from_aabb(
+                    &self.nodes[index + 1].bounding_box,
+                    &self.nodes[node.offset as usize].bounding_box,
+                )
+            };
+            self.nodes[index].bounding_box = bounding_box;
+        }
+
+        match max_degradation {
+            Some(max_degradation) if self.sah_cost() > max_degradation * self.build_cost => {
+                *self = Bvh::build(std::mem::take(&mut self.primitives));
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub fn stats(&self) -> BvhStats {
+        let mut stats = BvhStats {
+            nodes: self.nodes.len(),
+            leaves: 0,
+            max_depth: 0,
+            avg_depth: 0.0,
+            min_leaf_size: usize::MAX,
+            max_leaf_size: 0,
+            avg_leaf_size: 0.0,
+            sah_cost: self.sah_cost(),
+        };
+
+        let mut depth_sum = 0;
+        let mut stack = vec![(0, 0)];
+        while let Some((index, depth)) = stack.pop() {
+            let node = &self.nodes[index];
+            if node.count > 0 {
+                stats.leaves += 1;
+                stats.max_depth = stats.max_depth.max(depth);
+                stats.min_leaf_size = stats.min_leaf_size.min(node.count as usize);
+                stats.max_leaf_size = stats.max_leaf_size.max(node.count as usize);
+                depth_sum += depth;
+            } else {
+                stack.push((index + 1, depth + 1));
+                stack.push((node.offset as usize, depth + 1));
+            }
+        }
+
+        stats.avg_depth = depth_sum as f64 / stats.leaves as f64;
+        stats.avg_leaf_size = self.primitives.len() as f64 / stats.leaves as f64;
+        stats
+    }
+
+    /// Expected cost of tracing a ray through the tree, relative to intersecting one primitive
+    fn sah_cost(&self) -> f64 {
+        let root_area = self.nodes[0].bounding_box.surface_area();
+        if root_area <= 0.0 {
+            return self.primitives.len() as f64;
+        }
+
+        self.nodes
+            .iter()
+            .map(|node| {
+                let cost = if node.count > 0 {
+                    node.count as f64
+                } else {
+                    TRAVERSAL_COST
+                };
+                cost * node.bounding_box.surface_area() / root_area
+            })
+            .sum()
+    }
+}
+
+/// Build the nodes over primitives with the bounding `boxes`
+///
+/// Also returns the order of the primitives in the leaves, as indices into `boxes`.
+fn build_tree(boxes: Vec<Aabb>) -> (Vec<LinearNode>, Vec<u32>) {
+    let mut items: Vec<_> = boxes
+        .into_iter()
+        .enumerate()
+        .map(|(index, bounding_box)| BuildItem {
+            index,
+            centroid: bounding_box.centroid(),
+            bounding_box,
+        })
+        .collect();
+
+    let root = build_recursive(&mut items, 0, 0);
+
+    let mut nodes = Vec::with_capacity(2 * items.len());
+    flatten(&mut nodes, root);
+
+    (nodes, items.iter().map(|item| item.index as u32).collect())
+}
+
+/// Build the subtree over `items`
+///
+/// `offset` is the position of `items` in the list of all primitives.
+fn build_recursive(items: &mut [BuildItem], offset: usize, depth: usize) -> BuildNode {
+    let bounding_box = items[1..].iter().fold(items[0].bounding_box.clone(), |acc, item| {
+        Aabb::from_aabb(&acc, &item.bounding_box)
+    });
+
+    let split = if depth < MAX_SAH_DEPTH {
+        split_sah(items, &bounding_box)
+    } else {
+        split_median(items)
+    };
+
+    let Some((axis, mid)) = split else {
+        return BuildNode::Leaf {
+            bounding_box,
+            offset,
+            count: items.len(),
+        };
+    };
+
+    let (first, second) = items.split_at_mut(mid);
+    let children = if first.len().min(second.len()) >= MIN_PARALLEL_BUILD {
+        rayon::join(
+            || build_recursive(first, offset, depth + 1),
+            || build_recursive(second, offset + mid, depth + 1),
+        )
+    } else {
+        (
+            build_recursive(first, offset, depth + 1),
+            build_recursive(second, offset + mid, depth + 1),
+        )
+    };
+
+    BuildNode::Interior {
+        bounding_box,
+        axis,
+        children: Box::new([children.0, children.1]),
+    }
+}
+
+/// Append `node` and its subtree to `nodes` in depth first order
+fn flatten(nodes: &mut Vec<LinearNode>, node: BuildNode) {
+    match node {
+        BuildNode::Leaf {
+            bounding_box,
+            offset,
+            count,
+        } => nodes.push(LinearNode {
+            bounding_box,
+            offset: offset as u32,
+            count: count as u16,
+            axis: 0,
+        }),
+        BuildNode::Interior {
+            bounding_box,
+            axis,
+            children,
+        } => {
+            let index = nodes.len();
+            nodes.push(LinearNode {
+                bounding_box,
+                offset: 0,
+                count: 0,
+                axis: axis as u8,
+            });
+
+            let [first, second] = *children;
+            flatten(nodes, first);
+            nodes[index].offset = nodes.len() as u32;
+            flatten(nodes, second);
+        }
+    }
+}
+
+fn union(acc: Option<Aabb>, b: &Aabb) -> Aabb {
+    match acc {
+        Some(a) => Aabb::from_aabb(&a, b),
+        None => b.clone(),
+    }
+}
+
+fn centroid_bounds(items: &[BuildItem]) -> Aabb {
+    items[1..].iter().fold(
+        Aabb::from_points(&items[0].centroid, &items[0].centroid),
+        |acc, item| Aabb::from_aabb(&acc, &Aabb::from_points(&item.centroid, &item.centroid)),
+    )
+}
+
+fn bin_index(centroid: &Vec3d, centroid_bounds: &Aabb, axis: usize) -> usize {
+    let range = centroid_bounds.axis(axis);
+    let offset = (centroid.axis(axis) - range.start) / (range.end - range.start);
+
+    ((offset * SAH_BINS as f64) as usize).min(SAH_BINS - 1)
+}
+
+/// Reorder `items` into two non-empty parts with the lowest estimated traversal cost
+///
+/// Object centroids are sorted into `SAH_BINS` buckets along each axis, and every plane between two
+/// buckets is scored by `count * surface area` of both sides. Returns the split axis and the
+/// number of items on the first side, or `None` if `items` should become a leaf.
+fn split_sah(items: &mut [BuildItem], bounding_box: &Aabb) -> Option<(usize, usize)> {
+    if items.len() == 1 {
+        return None;
+    }
+
+    let centroid_bounds = centroid_bounds(items);
+
+    // (cost, axis, last bin on the first side)
+    let mut best: Option<(f64, usize, usize)> = None;
+
+    for axis in 0..3 {
+        let range = centroid_bounds.axis(axis);
+        if range.end - range.start <= 0.0 {
+            continue;
+        }
+
+        let mut counts = [0usize; SAH_BINS];
+        let mut bounds: [Option<Aabb>; SAH_BINS] = Default::default();
+        for item in items.iter() {
+            let bin = bin_index(&item.centroid, &centroid_bounds, axis);
+            counts[bin] += 1;
+            bounds[bin] = Some(union(bounds[bin].take(), &item.bounding_box));
+        }
+
+        // Sweep from the right to know the size of everything above each plane
+        let mut right_counts = [0usize; SAH_BINS];
+        let mut right_areas = [0.0; SAH_BINS];
+        let mut acc: Option<Aabb> = None;
+        let mut count = 0;
+        for bin in (1..SAH_BINS).rev() {
+            count += counts[bin];
+            if let Some(b) = &bounds[bin] {
+                acc = Some(union(acc, b));
+            }
+            right_counts[bin] = count;
+            right_areas[bin] = acc.as_ref().map_or(0.0, Aabb::surface_area);
+        }
+
+        let mut acc: Option<Aabb> = None;
+        let mut count = 0;
+        for bin in 0..SAH_BINS - 1 {
+            count += counts[bin];
+            if let Some(b) = &bounds[bin] {
+                acc = Some(union(acc, b));
+            }
+            if count == 0 || right_counts[bin + 1] == 0 {
+                continue;
+            }
+
+            let left_area = acc.as_ref().map_or(0.0, Aabb::surface_area);
+            let cost = count as f64 * left_area + right_counts[bin + 1] as f64 * right_areas[bin + 1];
+            if best.is_none_or(|(best_cost, _, _)| cost < best_cost) {
+                best = Some((cost, axis, bin));
+            }
+        }
+    }
+
+    let Some((cost, axis, split)) = best else {
+        // All centroids coincide, no plane can separate them
+        return split_median(items);
+    };
+
+    let area = bounding_box.surface_area();
+    let split_cost = TRAVERSAL_COST + if area > 0.0 { cost / area } else { items.len() as f64 };
+    if items.len() <= MAX_LEAF_SIZE && items.len() as f64 <= split_cost {
+        return None;
+    }
+
+    let mut mid = 0;
+    for i in 0..items.len() {
+        if bin_index(&items[i].centroid, &centroid_bounds, axis) <= split {
+            items.swap(i, mid);
+            mid += 1;
+        }
+    }
+
+    Some((axis, mid))
+}
+
+/// Split `items` in half along the axis with the largest centroid extent
+fn split_median(items: &mut [BuildItem]) -> Option<(usize, usize)> {
+    if items.len() <= MAX_LEAF_SIZE {
+        return None;
+    }
+
+    let centroid_bounds = centroid_bounds(items);
+    let axis = (0..3)
+        .max_by(|&a, &b| {
+            let extent = |axis| centroid_bounds.axis(axis).end - centroid_bounds.axis(axis).start;
+            extent(a).total_cmp(&extent(b))
+        })
+        .unwrap();
+
+    let mid = items.len() / 2;
+    items.select_nth_unstable_by(mid, |a, b| a.centroid.axis(axis).total_cmp(&b.centroid.axis(axis)));
+
+    Some((axis, mid))
+}
+
+impl<T: Hittable> Bvh<T> {
+    /// Closest hit of `ray`, counting the visited nodes and tested primitives if `COUNT` is set
+    #[inline]
+    fn traverse<const COUNT: bool>(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        let inv_direction = 1.0 / ray.direction;
+
+        let mut closest = None;
+        let mut closest_t = t_max;
+
+        let mut stack = [0usize; STACK_SIZE];
+        let mut stack_len = 0;
+        let mut current = 0;
+
+        loop {
+            let node = &self.nodes[current];
+            if COUNT {
+                stats::count_node();
+            }
+
+            if node.bounding_box.hit(ray, &inv_direction, t_min..closest_t) {
+                if node.count > 0 {
+                    let start = node.offset as usize;
+                    for primitive in &self.primitives[start..start + node.count as usize] {
+                        if let Some(hit) = stats::hit::<COUNT>(primitive, ray, t_min, closest_t) {
+                            closest_t = hit.t;
+                            closest = Some(hit);
+                        }
+                    }
+                } else {
+                    // Visit the child nearer to the ray origin first, hits there cut the far one short
+                    let (near, far) = if inv_direction.axis(node.axis as usize) < 0.0 {
+                        (node.offset as usize, current + 1)
+                    } else {
+                        (current + 1, node.offset as usize)
+                    };
+
+                    stack[stack_len] = far;
+                    stack_len += 1;
+                    current = near;
+                    continue;
+                }
+            }
+
+            if stack_len == 0 {
+                break;
+            }
+            stack_len -= 1;
+            current = stack[stack_len];
+        }
+
+        closest
+    }
+}
+
+impl<T: Hittable> Hittable for Bvh<T> {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        self.traverse::<false>(ray, t_min, t_max)
+    }
+
+    fn hit_counted(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        self.traverse::<true>(ray, t_min, t_max)
+    }
+
+    fn bounding_box(&self) -> Cow<'_, Aabb> {
+        Cow::Borrowed(&self.nodes[0].bounding_box)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{acceleration::tests::sphere, gemeometry::Sphere, v3d};
+    use approx::*;
+
+    #[test]
+    fn test_sah_isolates_distant_object() {
+        let mut spheres: Vec<_> = (0..8).map(|i| sphere(v3d!(i as f64 * 0.1, 0.0, 0.0), 0.1)).collect();
+        spheres.push(sphere(v3d!(100.0, 0.0, 0.0), 0.1));
+
+        let bvh = Bvh::build(spheres);
+        let root = &bvh.nodes[0];
+        assert_eq!(root.count, 0);
+
+        // The distant sphere is the second child, as a leaf of its own
+        let second = &bvh.nodes[root.offset as usize];
+        assert_eq!(second.count, 1);
+        assert_eq!(bvh.primitives[second.offset as usize].origin.x, 100.0);
+    }
+
+    #[test]
+    fn test_parallel_build_is_deterministic() {
+        let spheres: Vec<_> = (0..5000)
+            .map(|i| {
+                let i = i as f64;
+                sphere(v3d!((i * 7.3) % 100.0, (i * 3.1) % 60.0, (i * 1.7) % 40.0), 0.2)
+            })
+            .collect();
+
+        let a = Bvh::build(spheres.clone());
+        let b = Bvh::build(spheres);
+
+        assert_eq!(a.nodes.len(), b.nodes.len());
+        for (node_a, node_b) in a.nodes.iter().zip(&b.nodes) {
+            assert_eq!(
+                (node_a.offset, node_a.count, node_a.axis),
+                (node_b.offset, node_b.count, node_b.axis)
+            );
+        }
+        for (sphere_a, sphere_b) in a.primitives.iter().zip(&b.primitives) {
+            let (a, b) = (sphere_a.origin, sphere_b.origin);
+            assert_eq!((a.x, a.y, a.z), (b.x, b.y, b.z));
+        }
+    }
+
+    #[test]
+    fn test_stats() {
+        let spheres: Vec<_> = (0..8).map(|i| sphere(v3d!(i as f64 * 10.0, 0.0, 0.0), 0.4)).collect();
+        let stats = Bvh::build(spheres).stats();
+
+        // Far apart spheres of the same size end up in a balanced tree of single sphere leaves
+        assert_eq!(stats.nodes, 15);
+        assert_eq!(stats.leaves, 8);
+        assert_eq!(stats.max_depth, 3);
+        assert_relative_eq!(stats.avg_depth, 3.0);
+        assert_eq!((stats.min_leaf_size, stats.max_leaf_size), (1, 1));
+    }
+
+    #[test]
+    fn test_only_counted_hits_are_counted() {
+        let spheres: Vec<_> = (0..8).map(|i| sphere(v3d!(i as f64 * 10.0, 0.0, -5.0), 0.4)).collect();
+        let bvh = Bvh::build(spheres);
+        let ray = Ray {
+            origin: v3d!(30.0, 0.0, 0.0),
+            direction: v3d!(0.0, 0.0, -1.0),
+            time: 0.0,
+        };
+
+        stats::take_counters();
+        bvh.hit(&ray, 0.001, f64::INFINITY).unwrap();
+        let counters = stats::take_counters();
+        assert_eq!((counters.nodes, counters.primitives), (0, 0));
+
+        bvh.hit_counted(&ray, 0.001, f64::INFINITY).unwrap();
+        let counters = stats::take_counters();
+        assert!(counters.nodes >= 4);
+        assert_eq!(counters.primitives, 1);
+    }
+
+    #[test]
+    fn test_refit_follows_moved_primitives() {
+        let spheres: Vec<_> = (0..20).map(|i| sphere(v3d!(i as f64, 0.0, -5.0), 0.4)).collect();
+        let mut bvh = Bvh::build(spheres);
+
+        for sphere in bvh.primitives_mut() {
+            *sphere = Sphere::new(sphere.origin + v3d!(0.0, 10.0, 0.0), 0.4, sphere.material.clone());
+        }
+        assert!(!bvh.refit(None));
+
+        let ray = Ray {
+            origin: v3d!(3.0, 10.0, 0.0),
+            direction: v3d!(0.0, 0.0, -1.0),
+            time: 0.0,
+        };
+        assert_relative_eq!(bvh.hit(&ray, 0.001, f64::INFINITY).unwrap().t, 4.6);
+    }
+
+    #[test]
+    fn test_refit_rebuilds_degraded_tree() {
+        let spheres: Vec<_> = (0..20).map(|i| sphere(v3d!(i as f64, 0.0, 0.0), 0.4)).collect();
+        let mut bvh = Bvh::build(spheres);
+
+        // Move every other sphere far away, so most nodes now span a long distance
+        for sphere in bvh.primitives_mut() {
+            if sphere.origin.x as i32 % 2 == 1 {
+                *sphere = Sphere::new(sphere.origin - v3d!(40.0, 0.0, 0.0), 0.4, sphere.material.clone());
+            }
+        }
+
+        assert!(!bvh.refit(Some(100.0)));
+        assert!(bvh.refit(Some(1.5)));
+    }
+
+    #[test]
+    fn test_cached_build() {
+        let dir = std::env::temp_dir().join(format!("weekend-ray-bvh-cache-{}", std::process::id()));
+        let spheres: Vec<_> = (0..100)
+            .map(|i| sphere(v3d!((i * 7 % 13) as f64, (i * 3 % 11) as f64, 0.0), 0.3))
+            .collect();
+        let hash = bvh_cache::geometry_hash(
+            &spheres
+                .iter()
+                .map(|s| s.bounding_box().into_owned())
+                .collect::<Vec<_>>(),
+        );
+
+        let built = Bvh::build_cached(spheres.clone(), Some(&dir));
+        assert!(bvh_cache::read(&dir, hash, spheres.len()).is_some());
+
+        let loaded = Bvh::build_cached(spheres.clone(), Some(&dir));
+        assert_eq!(built.nodes.len(), loaded.nodes.len());
+        for (a, b) in built.primitives.iter().zip(&loaded.primitives) {
+            assert_eq!((a.origin.x, a.origin.y), (b.origin.x, b.origin.y));
+        }
+
+        // Changed geometry doesn't find the old tree
+        let mut moved = spheres.clone();
+        moved[0] = sphere(v3d!(50.0, 0.0, 0.0), 0.3);
+        let moved_boxes: Vec<_> = moved.iter().map(|s| s.bounding_box().into_owned()).collect();
+        assert!(bvh_cache::read(&dir, bvh_cache::geometry_hash(&moved_boxes), moved.len()).is_none());
+
+        // A damaged file is ignored
+        let file = dir.join(format!("{hash:016x}.bvh"));
+        let bytes = std::fs::read(&file).unwrap();
+        std::fs::write(&file, &bytes[..bytes.len() / 2]).unwrap();
+        assert!(bvh_cache::read(&dir, hash, spheres.len()).is_none());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_cache_rejects_too_deep_tree() {
+        let dir = std::env::temp_dir().join(format!("weekend-ray-bvh-cache-deep-{}", std::process::id()));
+        let bounding_box = Aabb::from_points(&v3d!(0.0, 0.0, 0.0), &v3d!(1.0, 1.0, 1.0));
+
+        // Chain of interior nodes, each with a leaf as first child and the next one as second
+        let chain = |depth: usize| {
+            let mut nodes = Vec::new();
+            for level in 0..depth {
+                nodes.push(LinearNode {
+                    bounding_box: bounding_box.clone(),
+                    offset: 2 * level as u32 + 2,
+                    count: 0,
+                    axis: 0,
+                });
+                nodes.push(LinearNode {
+                    bounding_box: bounding_box.clone(),
+                    offset: level as u32,
+                    count: 1,
+                    axis: 0,
+                });
+            }
+            nodes.push(LinearNode {
+                bounding_box: bounding_box.clone(),
+                offset: depth as u32,
+                count: 1,
+                axis: 0,
+            });
+            (nodes, (0..=depth as u32).collect::<Vec<_>>())
+        };
+
+        let (nodes, order) = chain(STACK_SIZE + 1);
+        bvh_cache::write(&dir, 1, &nodes, &order);
+        assert!(bvh_cache::read(&dir, 1, order.len()).is_none());
+
+        // The same tree one level less deep fits the stack
+        let (nodes, order) = chain(STACK_SIZE);
+        bvh_cache::write(&dir, 1, &nodes, &order);
+        assert!(bvh_cache::read(&dir, 1, order.len()).is_some());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_cache_rejects_bogus_node_count() {
+        let dir = std::env::temp_dir().join(format!("weekend-ray-bvh-cache-count-{}", std::process::id()));
+        let leaf = LinearNode {
+            bounding_box: Aabb::from_points(&v3d!(0.0, 0.0, 0.0), &v3d!(1.0, 1.0, 1.0)),
+            offset: 0,
+            count: 1,
+            axis: 0,
+        };
+
+        bvh_cache::write(&dir, 1, &[leaf], &[0]);
+        assert!(bvh_cache::read(&dir, 1, 1).is_some());
+
+        // The node count follows the magic, the hash and the primitive count
+        let file = dir.join(format!("{:016x}.bvh", 1));
+        let mut bytes = std::fs::read(&file).unwrap();
+        bytes[24..32].copy_from_slice(&u64::MAX.to_le_bytes());
+        std::fs::write(&file, bytes).unwrap();
+        assert!(bvh_cache::read(&dir, 1, 1).is_none());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
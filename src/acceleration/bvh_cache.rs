@@ -0,0 +1,187 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use super::{
+    bvh::{LinearNode, STACK_SIZE},
+    Aabb,
+};
+use crate::core::Vec3d;
+
+/// Start of every cache file, change the version when the layout or the builder changes
+const MAGIC: &[u8; 8] = b"WRBVH001";
+
+/// Bytes of a node in the file, two corners, offset, count and axis
+const NODE_SIZE: usize = 6 * 8 + 4 + 2 + 1;
+
+/// FNV-1a hash of the bounding boxes, the only input of the BVH build
+pub fn geometry_hash(boxes: &[Aabb]) -> u64 {
+    let mut hash = 0xcbf29ce484222325u64;
+
+    for b in boxes {
+        for axis in 0..3 {
+            for value in [b.axis(axis).start, b.axis(axis).end] {
+                for byte in value.to_bits().to_le_bytes() {
+                    hash = (hash ^ byte as u64).wrapping_mul(0x100000001b3);
+                }
+            }
+        }
+    }
+
+    hash
+}
+
+fn cache_file(dir: &Path, hash: u64) -> PathBuf {
+    dir.join(format!("{hash:016x}.bvh"))
+}
+
+/// Nodes and primitive order of a tree saved for the geometry with `hash`
+///
+/// Returns `None` if there is no such file, or it doesn't fit the geometry.
+pub fn read(dir: &Path, hash: u64, primitive_count: usize) -> Option<(Vec<LinearNode>, Vec<u32>)> {
+    let bytes = fs::read(cache_file(dir, hash)).ok()?;
+    let mut reader = Reader { bytes: &bytes };
+
+    if reader.take(MAGIC.len())? != MAGIC || reader.u64()? != hash || reader.u64()? != primitive_count as u64 {
+        return None;
+    }
+
+    let node_count = reader.u64()? as usize;
+
+    let mut order = Vec::with_capacity(primitive_count);
+    let mut seen = vec![false; primitive_count];
+    for _ in 0..primitive_count {
+        let index = reader.u32()?;
+        if std::mem::replace(seen.get_mut(index as usize)?, true) {
+            return None;
+        }
+        order.push(index);
+    }
+
+    // A tree has less than two nodes per primitive, larger counts are damaged and mustn't be allocated
+    if node_count > 2 * primitive_count || node_count > reader.bytes.len() / NODE_SIZE {
+        return None;
+    }
+
+    let mut nodes = Vec::with_capacity(node_count);
+    for index in 0..node_count {
+        let min = Vec3d {
+            x: reader.f64()?,
+            y: reader.f64()?,
+            z: reader.f64()?,
+        };
+        let max = Vec3d {
+            x: reader.f64()?,
+            y: reader.f64()?,
+            z: reader.f64()?,
+        };
+        let node = LinearNode {
+            bounding_box: Aabb::from_points(&min, &max),
+            offset: reader.u32()?,
+            count: reader.u16()?,
+            axis: reader.u8()?,
+        };
+
+        // Children and primitives must be in range, or traversing the tree would panic
+        let valid = if node.count > 0 {
+            node.offset as usize + node.count as usize <= primitive_count
+        } else {
+            index + 1 < node_count && (node.offset as usize) > index + 1 && (node.offset as usize) < node_count
+        };
+        if !valid || node.axis > 2 {
+            return None;
+        }
+
+        nodes.push(node);
+    }
+
+    if nodes.is_empty() || !reader.bytes.is_empty() {
+        return None;
+    }
+
+    // Deeper trees would overflow the traversal stack, which holds one node for every level
+    let mut depths = vec![0; nodes.len()];
+    for (index, node) in nodes.iter().enumerate() {
+        if node.count == 0 {
+            let depth = depths[index] + 1;
+            if depth > STACK_SIZE {
+                return None;
+            }
+
+            for child in [index + 1, node.offset as usize] {
+                depths[child] = depths[child].max(depth);
+            }
+        }
+    }
+
+    Some((nodes, order))
+}
+
+/// Save the tree for the geometry with `hash`, failures only print a warning
+pub fn write(dir: &Path, hash: u64, nodes: &[LinearNode], order: &[u32]) {
+    let mut bytes = Vec::with_capacity(32 + 4 * order.len() + NODE_SIZE * nodes.len());
+    bytes.extend_from_slice(MAGIC);
+    bytes.extend_from_slice(&hash.to_le_bytes());
+    bytes.extend_from_slice(&(order.len() as u64).to_le_bytes());
+    bytes.extend_from_slice(&(nodes.len() as u64).to_le_bytes());
+
+    for index in order {
+        bytes.extend_from_slice(&index.to_le_bytes());
+    }
+
+    for node in nodes {
+        let b = &node.bounding_box;
+        for value in [b.axis(0).start, b.axis(1).start, b.axis(2).start] {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        for value in [b.axis(0).end, b.axis(1).end, b.axis(2).end] {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        bytes.extend_from_slice(&node.offset.to_le_bytes());
+        bytes.extend_from_slice(&node.count.to_le_bytes());
+        bytes.push(node.axis);
+    }
+
+    let file = cache_file(dir, hash);
+    if let Err(err) = fs::create_dir_all(dir).and_then(|_| fs::write(&file, bytes)) {
+        eprintln!("Can't write BVH cache file '{}': {err}", file.display());
+    }
+}
+
+/// Little endian values from the start of a byte slice
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Option<&'a [u8]> {
+        if self.bytes.len() < n {
+            return None;
+        }
+
+        let (taken, rest) = self.bytes.split_at(n);
+        self.bytes = rest;
+        Some(taken)
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        Some(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Option<u16> {
+        Some(u16::from_le_bytes(self.take(2)?.try_into().ok()?))
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        Some(u32::from_le_bytes(self.take(4)?.try_into().ok()?))
+    }
+
+    fn u64(&mut self) -> Option<u64> {
+        Some(u64::from_le_bytes(self.take(8)?.try_into().ok()?))
+    }
+
+    fn f64(&mut self) -> Option<f64> {
+        Some(f64::from_le_bytes(self.take(8)?.try_into().ok()?))
+    }
+}
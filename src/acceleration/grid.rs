@@ -0,0 +1,263 @@
+use std::borrow::Cow;
+
+use super::{stats, Aabb};
+use crate::core::{HitRecord, Hittable, Ray};
+
+/// Aimed number of cells per primitive, the heuristic for the grid resolution
+const CELLS_PER_PRIMITIVE: f64 = 3.0;
+
+/// Maximum number of cells along each axis
+const MAX_RESOLUTION: usize = 128;
+
+/// Objects this many times larger than the median object are kept out of the grid, e.g. a huge
+/// ground sphere would otherwise stretch it and leave almost all other objects in a few cells
+const LARGE_OBJECT_FACTOR: f64 = 16.0;
+
+/// Uniform grid over a list of primitives
+///
+/// The bounding box of the scene is divided into equally sized cells, each listing the primitives
+/// overlapping it. Rays walk through the cells in order with a 3D-DDA, so the first cell containing
+/// a hit ends the search. Works best for many similarly sized objects spread evenly.
+pub struct Grid<T> {
+    primitives: Vec<T>,
+
+    /// Primitives much larger than the others, tested for every ray instead of being in the grid
+    large_primitives: Vec<u32>,
+
+    /// Bounds of the grid cells, without the large primitives
+    grid_box: Aabb,
+    bounding_box: Aabb,
+    resolution: [usize; 3],
+    cell_size: [f64; 3],
+
+    /// Start of the primitives of each cell in `cell_primitives`, with an extra entry for the end
+    cell_starts: Vec<u32>,
+    cell_primitives: Vec<u32>,
+}
+
+impl<T: Hittable> Grid<T> {
+    /// Build a grid over `primitives`, with about `CELLS_PER_PRIMITIVE` cubic cells per primitive
+    pub fn build(primitives: Vec<T>) -> Self {
+        if primitives.is_empty() {
+            panic!("Can't build a grid without any objects");
+        }
+
+        let boxes: Vec<_> = primitives.iter().map(|p| p.bounding_box().into_owned()).collect();
+        let bounding_box = union(boxes.iter());
+
+        let size = |b: &Aabb| {
+            (0..3)
+                .map(|axis| b.axis(axis).end - b.axis(axis).start)
+                .fold(0.0, f64::max)
+        };
+        let mut sizes: Vec<_> = boxes.iter().map(size).collect();
+        let mid = sizes.len() / 2;
+        let median_size = *sizes.select_nth_unstable_by(mid, f64::total_cmp).1;
+
+        let (large, small): (Vec<_>, Vec<_>) =
+            (0..boxes.len()).partition(|&index| size(&boxes[index]) > LARGE_OBJECT_FACTOR * median_size);
+        let grid_box = union(small.iter().map(|&index| &boxes[index])).pad(1e-4);
+
+        let extent = [0, 1, 2].map(|axis| grid_box.axis(axis).end - grid_box.axis(axis).start);
+        let cells_per_unit = (CELLS_PER_PRIMITIVE * small.len() as f64 / (extent[0] * extent[1] * extent[2])).cbrt();
+        let resolution = extent.map(|e| ((e * cells_per_unit).round() as usize).clamp(1, MAX_RESOLUTION));
+
+        let mut grid = Grid {
+            cell_size: [0, 1, 2].map(|axis| extent[axis] / resolution[axis] as f64),
+            primitives,
+            large_primitives: large.iter().map(|&index| index as u32).collect(),
+            grid_box,
+            bounding_box,
+            resolution,
+            cell_starts: Vec::new(),
+            cell_primitives: Vec::new(),
+        };
+
+        // Count the primitives of each cell first, to store all lists in a single Vec
+        let mut cell_starts = vec![0; resolution[0] * resolution[1] * resolution[2] + 1];
+        for &index in &small {
+            grid.for_each_cell(&boxes[index], |cell| cell_starts[cell + 1] += 1);
+        }
+        for cell in 1..cell_starts.len() {
+            cell_starts[cell] += cell_starts[cell - 1];
+        }
+
+        let mut next = cell_starts.clone();
+        let mut cell_primitives = vec![0; *cell_starts.last().unwrap() as usize];
+        for &index in &small {
+            grid.for_each_cell(&boxes[index], |cell| {
+                cell_primitives[next[cell] as usize] = index as u32;
+                next[cell] += 1;
+            });
+        }
+
+        grid.cell_starts = cell_starts;
+        grid.cell_primitives = cell_primitives;
+        grid
+    }
+
+    /// Take back the primitives, e.g. to move them and build a new grid
+    pub fn into_primitives(self) -> Vec<T> {
+        self.primitives
+    }
+
+    /// Index of the cell containing the coordinate `x` along `axis`, clamped to the grid
+    #[inline]
+    fn cell_coordinate(&self, x: f64, axis: usize) -> usize {
+        let offset = (x - self.grid_box.axis(axis).start) / self.cell_size[axis];
+        (offset.max(0.0) as usize).min(self.resolution[axis] - 1)
+    }
+
+    #[inline]
+    fn cell_index(&self, cell: [usize; 3]) -> usize {
+        (cell[2] * self.resolution[1] + cell[1]) * self.resolution[0] + cell[0]
+    }
+
+    /// Call `f` with the index of every cell overlapping `bounding_box`
+    fn for_each_cell(&self, bounding_box: &Aabb, mut f: impl FnMut(usize)) {
+        let [x, y, z] = [0, 1, 2].map(|axis| {
+            let range = bounding_box.axis(axis);
+            self.cell_coordinate(range.start, axis)..=self.cell_coordinate(range.end, axis)
+        });
+
+        for cz in z {
+            for cy in y.clone() {
+                for cx in x.clone() {
+                    f(self.cell_index([cx, cy, cz]));
+                }
+            }
+        }
+    }
+}
+
+impl<T: Hittable> Grid<T> {
+    /// Closest hit of `ray`, counting the visited cells and tested primitives if `COUNT` is set
+    #[inline]
+    fn traverse<const COUNT: bool>(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        let mut closest = None;
+        let mut closest_t = t_max;
+
+        for &primitive in &self.large_primitives {
+            if let Some(hit) = stats::hit::<COUNT>(&self.primitives[primitive as usize], ray, t_min, closest_t) {
+                closest_t = hit.t;
+                closest = Some(hit);
+            }
+        }
+
+        let inv_direction = 1.0 / ray.direction;
+        let Some(interval) = self.grid_box.hit_interval(ray, &inv_direction, t_min..closest_t) else {
+            return closest;
+        };
+
+        let entry = ray.at(interval.start);
+        let mut cell = [0; 3];
+        let mut step = [0isize; 3];
+        let mut t_next = [f64::INFINITY; 3];
+        let mut t_delta = [f64::INFINITY; 3];
+
+        for axis in 0..3 {
+            cell[axis] = self.cell_coordinate(entry.axis(axis), axis);
+
+            let inv_d = inv_direction.axis(axis);
+            let cell_start = self.grid_box.axis(axis).start + cell[axis] as f64 * self.cell_size[axis];
+            if inv_d > 0.0 && inv_d.is_finite() {
+                step[axis] = 1;
+                t_next[axis] = (cell_start + self.cell_size[axis] - ray.origin.axis(axis)) * inv_d;
+                t_delta[axis] = self.cell_size[axis] * inv_d;
+            } else if inv_d < 0.0 && inv_d.is_finite() {
+                step[axis] = -1;
+                t_next[axis] = (cell_start - ray.origin.axis(axis)) * inv_d;
+                t_delta[axis] = -self.cell_size[axis] * inv_d;
+            }
+        }
+
+        loop {
+            if COUNT {
+                stats::count_node();
+            }
+
+            let index = self.cell_index(cell);
+            let cell_primitives = self.cell_starts[index] as usize..self.cell_starts[index + 1] as usize;
+            for &primitive in &self.cell_primitives[cell_primitives] {
+                if let Some(hit) = stats::hit::<COUNT>(&self.primitives[primitive as usize], ray, t_min, closest_t) {
+                    closest_t = hit.t;
+                    closest = Some(hit);
+                }
+            }
+
+            let axis = if t_next[0] < t_next[1] {
+                if t_next[0] < t_next[2] {
+                    0
+                } else {
+                    2
+                }
+            } else if t_next[1] < t_next[2] {
+                1
+            } else {
+                2
+            };
+
+            // Cells further along the ray can't contain a closer hit
+            if closest_t <= t_next[axis] || t_next[axis] > interval.end {
+                break;
+            }
+
+            match cell[axis].checked_add_signed(step[axis]) {
+                Some(next) if next < self.resolution[axis] => cell[axis] = next,
+                _ => break,
+            }
+            t_next[axis] += t_delta[axis];
+        }
+
+        closest
+    }
+}
+
+impl<T: Hittable> Hittable for Grid<T> {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        self.traverse::<false>(ray, t_min, t_max)
+    }
+
+    fn hit_counted(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        self.traverse::<true>(ray, t_min, t_max)
+    }
+
+    fn bounding_box(&self) -> Cow<'_, Aabb> {
+        Cow::Borrowed(&self.bounding_box)
+    }
+}
+
+fn union<'b>(mut boxes: impl Iterator<Item = &'b Aabb>) -> Aabb {
+    let first = boxes.next().unwrap().clone();
+    boxes.fold(first, |acc, b| Aabb::from_aabb(&acc, b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{acceleration::tests::sphere, v3d};
+
+    #[test]
+    fn test_resolution() {
+        // 1000 spheres evenly spread in a cube should give about 3000 cubic cells
+        let spheres: Vec<_> = (0..1000)
+            .map(|i| sphere(v3d!((i % 10) as f64, (i / 10 % 10) as f64, (i / 100) as f64), 0.1))
+            .collect();
+        let grid = Grid::build(spheres);
+
+        assert_eq!(grid.resolution, [14, 14, 14]);
+        assert!(grid.cell_primitives.len() >= 1000);
+    }
+
+    #[test]
+    fn test_large_objects_are_kept_out() {
+        let mut spheres: Vec<_> = (0..50)
+            .map(|i| sphere(v3d!(i as f64 % 10.0, 0.0, -10.0), 0.4))
+            .collect();
+        spheres.push(sphere(v3d!(0.0, -1002.5, -10.0), 1000.0));
+
+        let grid = Grid::build(spheres);
+        assert_eq!(grid.large_primitives, [50]);
+        assert!(grid.grid_box.axis(1).start > -1.0);
+    }
+}
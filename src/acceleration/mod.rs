@@ -1,5 +1,69 @@
 mod aabb;
-mod bvh_node;
+mod bvh;
+mod bvh_cache;
+mod grid;
+pub mod stats;
 
 pub use aabb::Aabb;
-pub use bvh_node::BvhNode;
+pub use bvh::{Bvh, MAX_BVH_DEGRADATION};
+pub use grid::Grid;
+pub use stats::{count_primitive, take_counters};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        color,
+        core::{Hittable, Ray, Vec3d},
+        gemeometry::Sphere,
+        material::{Lambertian, MaterialConfig},
+        texture::Texture,
+        v3d,
+    };
+
+    pub(super) fn sphere(origin: Vec3d, radius: f64) -> Sphere {
+        Sphere::new(
+            origin,
+            radius,
+            MaterialConfig::Lambertian(Lambertian {
+                albedo: Texture::Solid(color!(0.5, 0.5, 0.5)),
+            }),
+        )
+    }
+
+    #[test]
+    fn test_hit_matches_linear_search() {
+        let mut spheres: Vec<_> = (0..50)
+            .map(|i| {
+                let i = i as f64;
+                sphere(
+                    v3d!((i * 7.3) % 10.0 - 5.0, (i * 3.1) % 6.0 - 3.0, -10.0 - i % 4.0),
+                    0.4,
+                )
+            })
+            .collect();
+        // Ground, kept out of the grid
+        spheres.push(sphere(v3d!(0.0, -1002.5, -10.0), 1000.0));
+
+        let bvh = Bvh::build(spheres.clone());
+        let grid = Grid::build(spheres.clone());
+
+        for x in -20..=20 {
+            for y in -12..=12 {
+                let ray = Ray {
+                    origin: v3d!(0.0, 0.0, 0.0),
+                    direction: v3d!(x as f64 * 0.025, y as f64 * 0.025, -1.0),
+                    time: 0.0,
+                };
+
+                let expected = spheres
+                    .iter()
+                    .filter_map(|s| s.hit(&ray, 0.001, f64::INFINITY))
+                    .map(|hit| hit.t)
+                    .min_by(f64::total_cmp);
+                assert_eq!(expected, bvh.hit(&ray, 0.001, f64::INFINITY).map(|hit| hit.t));
+                assert_eq!(expected, grid.hit(&ray, 0.001, f64::INFINITY).map(|hit| hit.t));
+            }
+        }
+    }
+}
@@ -0,0 +1,57 @@
+use std::cell::Cell;
+
+use crate::core::{HitRecord, Hittable, Ray};
+
+/// Work done for tracing rays on the current thread
+#[derive(Clone, Copy, Default)]
+pub struct TraversalCounters {
+    /// Bounding boxes tested
+    pub nodes: u64,
+
+    /// Primitives tested
+    pub primitives: u64,
+}
+
+thread_local! {
+    static COUNTERS: Cell<TraversalCounters> = const { Cell::new(TraversalCounters { nodes: 0, primitives: 0 }) };
+}
+
+#[inline]
+pub fn count_node() {
+    COUNTERS.with(|counters| {
+        let mut c = counters.get();
+        c.nodes += 1;
+        counters.set(c);
+    });
+}
+
+#[inline]
+pub fn count_primitive() {
+    COUNTERS.with(|counters| {
+        let mut c = counters.get();
+        c.primitives += 1;
+        counters.set(c);
+    });
+}
+
+/// `hit_counted` of `object` if `COUNT` is set, else the plain `hit`
+///
+/// Lets traversals choose at compile time whether they count their work.
+#[inline(always)]
+pub fn hit<'a, const COUNT: bool>(
+    object: &'a impl Hittable,
+    ray: &Ray,
+    t_min: f64,
+    t_max: f64,
+) -> Option<HitRecord<'a>> {
+    if COUNT {
+        object.hit_counted(ray, t_min, t_max)
+    } else {
+        object.hit(ray, t_min, t_max)
+    }
+}
+
+/// Counters of the current thread since the last call, resets them
+pub fn take_counters() -> TraversalCounters {
+    COUNTERS.with(|counters| counters.take())
+}
@@ -0,0 +1,349 @@
+use std::{f64::consts::PI, sync::Arc};
+
+use crate::core::{color::luminance, Color, Vec3d};
+use crate::{color, v3d};
+
+/// Image based lighting from an equirectangular (latitude-longitude) environment map
+///
+/// The image is loaded while reading the scene file, so a missing or broken file is reported
+/// before rendering starts.
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+#[serde(try_from = "EnvironmentMapFile")]
+pub struct EnvironmentMap {
+    /// Path to a Radiance `.hdr` or OpenEXR `.exr` image
+    pub file_path: String,
+
+    /// Rotation of the map around the vertical axis in degrees
+    pub rotation: f64,
+
+    /// Scale factor for the radiance read from the image
+    pub intensity: f64,
+
+    #[serde(skip)]
+    image: Arc<HdrImage>,
+
+    #[serde(skip)]
+    sampler: Arc<EnvironmentSampler>,
+}
+
+/// `EnvironmentMap` as written in the scene file
+#[derive(serde::Deserialize)]
+struct EnvironmentMapFile {
+    file_path: String,
+
+    #[serde(default)]
+    rotation: f64,
+
+    #[serde(default = "default_intensity")]
+    intensity: f64,
+}
+
+/// Direction sampled proportional to the brightness of the environment map
+pub struct LightSample {
+    pub direction: Vec3d,
+    pub radiance: Color,
+
+    /// Probability density with respect to solid angle
+    pub pdf: f64,
+}
+
+/// Piecewise constant 2D distribution over the pixels of the map
+///
+/// Every pixel is weighted by its luminance and the `sin(theta)` factor of the
+/// equirectangular projection, rows are selected with the marginal distribution
+/// and the column with the conditional distribution of that row.
+#[derive(Clone)]
+struct EnvironmentSampler {
+    /// Cumulative distribution over the rows, `height + 1` entries
+    marginal_cdf: Vec<f64>,
+
+    /// Cumulative distribution inside each row, `height * (width + 1)` entries
+    conditional_cdf: Vec<f64>,
+
+    /// Probability of picking each pixel
+    pmf: Vec<f64>,
+}
+
+/// Linear radiance values of the loaded image, stored row by row from the top
+#[derive(Clone)]
+pub struct HdrImage {
+    pub width: usize,
+    pub height: usize,
+    pixels: Vec<[f32; 3]>,
+}
+
+fn default_intensity() -> f64 {
+    1.0
+}
+
+impl TryFrom<EnvironmentMapFile> for EnvironmentMap {
+    type Error = String;
+
+    fn try_from(file: EnvironmentMapFile) -> Result<Self, String> {
+        let image = HdrImage::load(&file.file_path)?;
+        Ok(EnvironmentMap::new(
+            file.file_path,
+            file.rotation,
+            file.intensity,
+            image,
+        ))
+    }
+}
+
+impl EnvironmentMap {
+    /// Environment map of an already loaded image, `file_path` is only kept for saving the scene
+    pub fn new(file_path: String, rotation: f64, intensity: f64, image: HdrImage) -> Self {
+        EnvironmentMap {
+            file_path,
+            rotation,
+            intensity,
+            sampler: Arc::new(EnvironmentSampler::new(&image)),
+            image: Arc::new(image),
+        }
+    }
+
+    pub fn image(&self) -> &HdrImage {
+        &self.image
+    }
+
+    fn sampler(&self) -> &EnvironmentSampler {
+        &self.sampler
+    }
+
+    /// Radiance arriving from the (not necessarily normalized) `direction`
+    #[inline]
+    pub fn radiance(&self, direction: &Vec3d) -> Color {
+        let (u, v) = self.direction_to_uv(direction);
+        self.image().pixel_at(u, v) * self.intensity
+    }
+
+    /// Map a direction to texture coordinates in [0, 1), v = 0 is straight up
+    #[inline]
+    pub fn direction_to_uv(&self, direction: &Vec3d) -> (f64, f64) {
+        let d = direction.unit_vector();
+
+        let theta = d.y.clamp(-1.0, 1.0).acos();
+        let phi = d.x.atan2(-d.z) + PI;
+
+        let u = (phi / (2.0 * PI) + self.rotation / 360.0).rem_euclid(1.0);
+        let v = theta / PI;
+
+        (u, v)
+    }
+
+    /// Map texture coordinates back to a unit direction, inverse of `direction_to_uv`
+    #[inline]
+    pub fn uv_to_direction(&self, u: f64, v: f64) -> Vec3d {
+        let phi = (u - self.rotation / 360.0).rem_euclid(1.0) * 2.0 * PI - PI;
+        let theta = v * PI;
+
+        v3d!(theta.sin() * phi.sin(), theta.cos(), -theta.sin() * phi.cos())
+    }
+
+    /// Importance sample an incoming direction using the uniform random numbers `u1` and `u2`
+    pub fn sample(&self, u1: f64, u2: f64) -> Option<LightSample> {
+        let image = self.image();
+        let sampler = self.sampler();
+
+        let (y, v_frac) = sample_cdf(&sampler.marginal_cdf, u1);
+        let row = &sampler.conditional_cdf[y * (image.width + 1)..(y + 1) * (image.width + 1)];
+        let (x, u_frac) = sample_cdf(row, u2);
+
+        let u = (x as f64 + u_frac) / image.width as f64;
+        let v = (y as f64 + v_frac) / image.height as f64;
+
+        let sin_theta = (v * PI).sin();
+        if sin_theta <= 0.0 {
+            return None;
+        }
+
+        let pdf_uv = sampler.pmf[y * image.width + x] * (image.width * image.height) as f64;
+
+        Some(LightSample {
+            direction: self.uv_to_direction(u, v),
+            radiance: image.pixel(x, y) * self.intensity,
+            pdf: pdf_uv / (2.0 * PI * PI * sin_theta),
+        })
+    }
+
+    /// Probability density of `sample` returning `direction`, with respect to solid angle
+    pub fn pdf(&self, direction: &Vec3d) -> f64 {
+        let image = self.image();
+        let (u, v) = self.direction_to_uv(direction);
+
+        let sin_theta = (v * PI).sin();
+        if sin_theta <= 0.0 {
+            return 0.0;
+        }
+
+        let x = ((u * image.width as f64) as usize).min(image.width - 1);
+        let y = ((v * image.height as f64) as usize).min(image.height - 1);
+
+        let pdf_uv = self.sampler().pmf[y * image.width + x] * (image.width * image.height) as f64;
+        pdf_uv / (2.0 * PI * PI * sin_theta)
+    }
+}
+
+impl EnvironmentSampler {
+    fn new(image: &HdrImage) -> Self {
+        let mut weights = Vec::with_capacity(image.width * image.height);
+
+        for y in 0..image.height {
+            let sin_theta = ((y as f64 + 0.5) / image.height as f64 * PI).sin();
+
+            for x in 0..image.width {
+                weights.push(luminance(&image.pixel(x, y)).max(0.0) * sin_theta);
+            }
+        }
+
+        let total: f64 = weights.iter().sum();
+
+        // A completely black map can't be importance sampled, fall back to uniform weights
+        let pmf = if total > 0.0 {
+            weights.iter().map(|w| w / total).collect::<Vec<_>>()
+        } else {
+            vec![1.0 / weights.len() as f64; weights.len()]
+        };
+
+        let mut marginal_cdf = Vec::with_capacity(image.height + 1);
+        let mut conditional_cdf = Vec::with_capacity(image.height * (image.width + 1));
+        marginal_cdf.push(0.0);
+
+        for row in pmf.chunks(image.width) {
+            let row_sum: f64 = row.iter().sum();
+            marginal_cdf.push(marginal_cdf.last().unwrap() + row_sum);
+
+            let mut acc = 0.0;
+            conditional_cdf.push(0.0);
+            for p in row {
+                acc += if row_sum > 0.0 {
+                    p / row_sum
+                } else {
+                    1.0 / row.len() as f64
+                };
+                conditional_cdf.push(acc);
+            }
+        }
+
+        EnvironmentSampler {
+            marginal_cdf,
+            conditional_cdf,
+            pmf,
+        }
+    }
+}
+
+/// Find the interval of the cumulative distribution `cdf` containing `u`
+///
+/// Returns the index of the interval and the relative position of `u` inside it.
+fn sample_cdf(cdf: &[f64], u: f64) -> (usize, f64) {
+    let u = u * cdf[cdf.len() - 1];
+    let index = (cdf.partition_point(|&c| c <= u) - 1).min(cdf.len() - 2);
+
+    let width = cdf[index + 1] - cdf[index];
+    let frac = if width > 0.0 { (u - cdf[index]) / width } else { 0.5 };
+
+    (index, frac.clamp(0.0, 1.0 - f64::EPSILON))
+}
+
+impl HdrImage {
+    fn load(file_path: &str) -> Result<Self, String> {
+        let img = image::open(file_path)
+            .map_err(|err| format!("Unable to read environment map '{file_path}': {err}"))?
+            .into_rgb32f();
+
+        Ok(HdrImage {
+            width: img.width() as usize,
+            height: img.height() as usize,
+            pixels: img.pixels().map(|p| p.0).collect(),
+        })
+    }
+
+    /// Nearest pixel for the texture coordinates `u` and `v`
+    #[inline]
+    pub fn pixel_at(&self, u: f64, v: f64) -> Color {
+        let x = ((u * self.width as f64) as usize).min(self.width - 1);
+        let y = ((v * self.height as f64) as usize).min(self.height - 1);
+
+        self.pixel(x, y)
+    }
+
+    #[inline]
+    pub fn pixel(&self, x: usize, y: usize) -> Color {
+        let [r, g, b] = self.pixels[y * self.width + x];
+        color!(r as f64, g as f64, b as f64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::*;
+    use rand::{rngs::SmallRng, Rng, SeedableRng};
+
+    fn test_map() -> EnvironmentMap {
+        let (width, height) = (16, 8);
+        let pixels = (0..width * height)
+            .map(|i| {
+                if i == 2 * width + 5 {
+                    [50.0, 40.0, 30.0]
+                } else {
+                    [0.2, 0.3, 0.5]
+                }
+            })
+            .collect();
+
+        EnvironmentMap::new(String::new(), 30.0, 1.0, HdrImage { width, height, pixels })
+    }
+
+    #[test]
+    fn test_missing_file_fails_to_load() {
+        let result = serde_json::from_value::<EnvironmentMap>(serde_json::json!({ "file_path": "missing.hdr" }));
+        assert!(result.is_err_and(|err| err.to_string().contains("missing.hdr")));
+    }
+
+    #[test]
+    fn test_uv_direction_roundtrip() {
+        let env = test_map();
+
+        for (u, v) in [(0.1, 0.2), (0.5, 0.5), (0.9, 0.7), (0.33, 0.95)] {
+            let (u2, v2) = env.direction_to_uv(&env.uv_to_direction(u, v));
+            assert_relative_eq!(u, u2, epsilon = 1e-9);
+            assert_relative_eq!(v, v2, epsilon = 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_sample_pdf_matches_pdf() {
+        let env = test_map();
+        let mut rng = SmallRng::seed_from_u64(1);
+
+        for _ in 0..100 {
+            if let Some(sample) = env.sample(rng.gen(), rng.gen()) {
+                assert_relative_eq!(sample.pdf, env.pdf(&sample.direction), max_relative = 1e-6);
+            }
+        }
+    }
+
+    #[test]
+    fn test_pdf_integrates_to_one() {
+        let env = test_map();
+
+        // Midpoint rule over theta and phi
+        let n = 400;
+        let d_theta = PI / n as f64;
+        let d_phi = 2.0 * PI / (2 * n) as f64;
+
+        let mut sum = 0.0;
+        for i in 0..n {
+            let theta = (i as f64 + 0.5) * d_theta;
+            for j in 0..2 * n {
+                let phi = (j as f64 + 0.5) * d_phi;
+                let direction = v3d!(theta.sin() * phi.cos(), theta.cos(), theta.sin() * phi.sin());
+                sum += env.pdf(&direction) * theta.sin() * d_theta * d_phi;
+            }
+        }
+
+        assert_relative_eq!(sum, 1.0, epsilon = 1e-2);
+    }
+}
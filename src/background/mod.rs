@@ -0,0 +1,58 @@
+mod environment;
+
+pub use environment::EnvironmentMap;
+
+use crate::color;
+use crate::core::{Color, Ray};
+
+/// Light returned for rays that don't hit any object
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+pub enum Background {
+    /// Constant color in every direction
+    Solid(Color),
+
+    /// Vertical gradient, blended by the y component of the ray direction
+    Gradient { bottom: Color, top: Color },
+
+    /// Equirectangular HDR image surrounding the scene
+    Environment(EnvironmentMap),
+
+    /// No light at all, the scene is only lit by emissive objects
+    Black,
+}
+
+impl Default for Background {
+    /// The white to blue sky from the book
+    fn default() -> Self {
+        Background::Gradient {
+            bottom: color!(1.0, 1.0, 1.0),
+            top: color!(0.5, 0.7, 1.0),
+        }
+    }
+}
+
+impl Background {
+    /// Color of the background seen in the direction of `ray`
+    #[inline]
+    pub fn color(&self, ray: &Ray) -> Color {
+        match self {
+            Background::Solid(color) => *color,
+            Background::Gradient { bottom, top } => {
+                let unit_direction = ray.direction.unit_vector();
+                let t = 0.5 * (unit_direction.y + 1.0);
+                bottom * (1.0 - t) + top * t
+            }
+            Background::Environment(env) => env.radiance(&ray.direction),
+            Background::Black => color!(0.0, 0.0, 0.0),
+        }
+    }
+
+    /// The environment map, if the background can be importance sampled
+    #[inline]
+    pub fn environment(&self) -> Option<&EnvironmentMap> {
+        match self {
+            Background::Environment(env) => Some(env),
+            _ => None,
+        }
+    }
+}
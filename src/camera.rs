@@ -4,7 +4,9 @@ use indicatif::{ParallelProgressIterator, ProgressBar, ProgressDrawTarget, Progr
 use rand::prelude::*;
 use rayon::prelude::*;
 
-use crate::core::{Color, Hittable, Ray, Vec3d};
+use crate::acceleration;
+use crate::background::{Background, EnvironmentMap};
+use crate::core::{Color, HitRecord, Hittable, Ray, Vec3d};
 use crate::{color, v3d_zero};
 
 use crate::material::Material;
@@ -13,13 +15,18 @@ use crate::material::Material;
 pub struct Camera {
     pub cfg: CameraConfig,
 
+    /// Light for rays which don't hit any object
+    pub background: Background,
+
     /// Rendered image width in pixel count
     pub image_width: u16,
 
     // Rendered image height in pixel count
     pub image_height: u16,
 
+    #[allow(dead_code)]
     pub viewport_width: f64,
+    #[allow(dead_code)]
     pub viewport_height: f64,
 
     /// Number of random samples for each pixel
@@ -66,7 +73,13 @@ pub struct CameraConfig {
 }
 
 impl Camera {
-    pub fn new(cfg: &CameraConfig, image_width: u16, samples_per_pixel: u16, depth: u16) -> Self {
+    pub fn new(
+        cfg: &CameraConfig,
+        background: &Background,
+        image_width: u16,
+        samples_per_pixel: u16,
+        depth: u16,
+    ) -> Self {
         // Determine the viewport
         let theta = cfg.vfov.to_radians();
         let viewport_height = (theta / 2.0).tan() * 2.0 * cfg.focus_dist;
@@ -96,6 +109,7 @@ impl Camera {
 
         Self {
             cfg: cfg.clone(),
+            background: background.clone(),
             image_width,
             image_height,
             viewport_width,
@@ -112,6 +126,32 @@ impl Camera {
 
     /// Render the given `objects`
     pub fn render(&self, objects: &(impl Hittable + Sync), quiet: bool) -> Vec<Color> {
+        self.render_lines(quiet, |h, line, rng| self.render_line(h, line, objects, rng))
+    }
+
+    /// Render the number of bounding box and primitive tests for each primary ray
+    ///
+    /// The counts are shown in false colors from blue (no tests) to red, returns the image and the
+    /// count shown as red.
+    pub fn render_heatmap(&self, objects: &(impl Hittable + Sync), quiet: bool) -> (Vec<Color>, u64) {
+        let counts = self.render_lines(quiet, |h, line, rng| {
+            for w in 0..self.image_width {
+                acceleration::take_counters();
+                objects.hit_counted(&self.get_ray(w, h, rng), 0.0001, f64::INFINITY);
+                let counters = acceleration::take_counters();
+
+                line[w as usize].x = (counters.nodes + counters.primitives) as f64;
+            }
+        });
+
+        let max = counts.iter().map(|count| count.x as u64).max().unwrap_or(0).max(1);
+        let img = counts.iter().map(|count| heat_color(count.x / max as f64)).collect();
+
+        (img, max)
+    }
+
+    /// Call `render_line` for all lines of the image in parallel
+    fn render_lines(&self, quiet: bool, render_line: impl Fn(u16, &mut [Vec3d], &mut SmallRng) + Sync) -> Vec<Color> {
         let mut img = vec![v3d_zero!(); self.image_height as usize * self.image_width as usize];
 
         let sty = ProgressStyle::with_template("[{elapsed_precise}] {bar:60.cyan/blue} {pos:>7}/{len:7} {msg}")
@@ -133,7 +173,7 @@ impl Camera {
             .progress_with(p)
             .enumerate()
             .for_each_init(SmallRng::from_entropy, |rng, (h, line)| {
-                self.render_line(h as u16, line, objects, rng)
+                render_line(h as u16, line, rng)
             });
 
         img
@@ -145,25 +185,9 @@ impl Camera {
         for w in 0..self.image_width {
             let mut color = v3d_zero!();
 
-            // Get a randomly-sampled camera ray for the pixel at location i,j, originating from
-            // the camera defocus disk.
             for _ in 0..self.samples_per_pixel {
-                let pixel_center = self.pixel00_loc + (w as f64 * self.pixel_delta_u) + (h as f64 * self.pixel_delta_v);
-                let pixel_sample = pixel_center + self.pixel_sample_square(&mut thread_rng);
-
-                let camera_origin = if self.cfg.defocus_angle <= 0.0 {
-                    self.cfg.look_from
-                } else {
-                    self.defocus_disk_sample(&mut thread_rng)
-                };
-
-                let r = Ray {
-                    origin: camera_origin,
-                    direction: pixel_sample - camera_origin,
-                    time: thread_rng.gen_range(0.0..=1.0),
-                };
-
-                color += Camera::ray_color(&r, self.max_depth, &mut thread_rng, objects);
+                let r = self.get_ray(w, h, &mut thread_rng);
+                color += self.ray_color(&r, self.max_depth, &mut thread_rng, objects, None);
             }
 
             color.x = Camera::linear_to_gamma(color.x * scale);
@@ -174,27 +198,117 @@ impl Camera {
         }
     }
 
+    /// Get a randomly-sampled camera ray for the pixel at location w,h, originating from the camera
+    /// defocus disk.
     #[inline(always)]
-    fn ray_color(ray: &Ray, depth: u16, rng: &mut impl rand::Rng, world: &impl Hittable) -> Color {
+    pub fn get_ray(&self, w: u16, h: u16, mut rng: &mut impl rand::Rng) -> Ray {
+        let pixel_center = self.pixel00_loc + (w as f64 * self.pixel_delta_u) + (h as f64 * self.pixel_delta_v);
+        let pixel_sample = pixel_center + self.pixel_sample_square(&mut rng);
+
+        let camera_origin = if self.cfg.defocus_angle <= 0.0 {
+            self.cfg.look_from
+        } else {
+            self.defocus_disk_sample(&mut rng)
+        };
+
+        Ray {
+            origin: camera_origin,
+            direction: pixel_sample - camera_origin,
+            time: rng.gen_range(0.0..=1.0),
+        }
+    }
+
+    /// Gather the light arriving along `ray`
+    ///
+    /// `bsdf_pdf` is the density with which the previous hit sampled `ray`, if that hit already
+    /// sampled the environment map directly. Light from the environment is then weighted with
+    /// multiple importance sampling to not count it twice.
+    #[inline(always)]
+    fn ray_color(
+        &self,
+        ray: &Ray,
+        depth: u16,
+        rng: &mut impl rand::Rng,
+        world: &impl Hittable,
+        bsdf_pdf: Option<f64>,
+    ) -> Color {
         // If we've exceeded the ray bounce limit, no more light is gathered.
         if depth == 0 {
             return color!(0.0, 0.0, 0.0);
         }
 
+        let Some(hit) = world.hit(ray, 0.0001, f64::INFINITY) else {
+            let background = self.background.color(ray);
+
+            return match (bsdf_pdf, self.background.environment()) {
+                (Some(bsdf_pdf), Some(env)) => background * power_heuristic(bsdf_pdf, env.pdf(&ray.direction)),
+                _ => background,
+            };
+        };
+
+        let mut color = hit.material.emitted(&hit);
+
+        // Sample the environment map directly for materials which support it
+        let env = self.background.environment();
+        let sample_lights = env.is_some() && hit.material.eval(ray, &hit, &hit.shading_normal).is_some();
+
+        // The material sample of the last bounce isn't traced, so the light sample can't share the
+        // environment light with it and counts fully
+        let last_bounce = depth == 1;
+
+        if let (true, Some(env)) = (sample_lights, env) {
+            color += Camera::direct_environment_light(env, ray, &hit, rng, world, !last_bounce);
+        }
+
         let mut rng_func = |range: Range<f64>| rng.gen_range(range);
 
-        if let Some(hit) = world.hit(ray, 0.0001, f64::INFINITY) {
-            if let Some(scatter) = hit.material.scatter(&mut rng_func, ray, &hit) {
-                return scatter.attenuation * Camera::ray_color(&scatter.ray, depth - 1, rng, world);
-            }
+        if let Some(scatter) = hit.material.scatter(&mut rng_func, ray, &hit) {
+            let scatter_pdf = if sample_lights {
+                hit.material
+                    .eval(ray, &hit, &scatter.ray.direction)
+                    .map(|eval| eval.pdf)
+            } else {
+                None
+            };
+
+            color += scatter.attenuation * self.ray_color(&scatter.ray, depth - 1, rng, world, scatter_pdf);
+        }
+
+        color
+    }
 
+    /// Light arriving directly from a point on the environment map
+    ///
+    /// With `mis` the light is weighted against material sampling, which finds the same light.
+    fn direct_environment_light(
+        env: &EnvironmentMap,
+        ray: &Ray,
+        hit: &HitRecord,
+        rng: &mut impl rand::Rng,
+        world: &impl Hittable,
+        mis: bool,
+    ) -> Color {
+        let Some(light) = env.sample(rng.gen(), rng.gen()) else {
+            return color!(0.0, 0.0, 0.0);
+        };
+
+        let Some(eval) = hit.material.eval(ray, hit, &light.direction) else {
+            return color!(0.0, 0.0, 0.0);
+        };
+
+        if eval.pdf <= 0.0 || light.pdf <= 0.0 {
             return color!(0.0, 0.0, 0.0);
         }
 
-        // blue sky background
-        let unit_direction = ray.direction.unit_vector();
-        let t = 0.5 * (unit_direction.y + 1.0);
-        color!(1.0, 1.0, 1.0) * (1.0 - t) + color!(0.5, 0.7, 1.0) * t
+        let shadow_ray = hit.spawn_ray(light.direction, ray.time);
+
+        if world.hit(&shadow_ray, 0.0001, f64::INFINITY).is_some() {
+            return color!(0.0, 0.0, 0.0);
+        }
+
+        let weight = if mis { power_heuristic(light.pdf, eval.pdf) } else { 1.0 };
+
+        eval.value * light.radiance * (weight / light.pdf)
     }
 
     /// Returns a random point in the camera defocus disk.
@@ -215,3 +329,35 @@ impl Camera {
         linear_value.sqrt()
     }
 }
+
+/// False color for `t` in [0, 1], going from blue over cyan, green and yellow to red
+fn heat_color(t: f64) -> Color {
+    const RAMP: [Color; 5] = [
+        color!(0.0, 0.0, 1.0),
+        color!(0.0, 1.0, 1.0),
+        color!(0.0, 1.0, 0.0),
+        color!(1.0, 1.0, 0.0),
+        color!(1.0, 0.0, 0.0),
+    ];
+
+    let x = t.clamp(0.0, 1.0) * (RAMP.len() - 1) as f64;
+    let i = (x as usize).min(RAMP.len() - 2);
+    let f = x - i as f64;
+
+    (1.0 - f) * RAMP[i] + f * RAMP[i + 1]
+}
+
+/// Weight for a sample with density `pdf_a`, when the same light could also be found with density `pdf_b`
+///
+/// Veach's power heuristic with an exponent of 2.
+#[inline]
+fn power_heuristic(pdf_a: f64, pdf_b: f64) -> f64 {
+    let a = pdf_a * pdf_a;
+    let b = pdf_b * pdf_b;
+
+    if a + b > 0.0 {
+        a / (a + b)
+    } else {
+        0.0
+    }
+}
@@ -22,3 +22,9 @@ pub fn random_range(rng: &mut impl rand::Rng, range: Range<f64>) -> Color {
         rng.gen_range(range)
     )
 }
+
+/// Relative luminance of a linear rgb color (Rec. 709 weights)
+#[inline]
+pub fn luminance(color: &Color) -> f64 {
+    0.2126 * color.x + 0.7152 * color.y + 0.0722 * color.z
+}
@@ -1,27 +1,104 @@
 use std::borrow::Cow;
 
-use crate::{acceleration::Aabb, core::Ray, core::Vec3d, material::MaterialConfig};
+use crate::{
+    acceleration::{self, Aabb},
+    core::Color,
+    core::Ray,
+    core::Vec3d,
+    material::MaterialConfig,
+};
 
 pub struct HitRecord<'mat> {
     pub point: Vec3d,
+
+    /// Normal of the actual surface, facing against the ray
     pub normal: Vec3d,
+
+    /// Normal used by the materials, e.g. interpolated from mesh vertices or perturbed by a normal
+    /// map, always on the same side as `normal`
+    pub shading_normal: Vec3d,
+
+    /// Derivatives of the point by the surface coordinates `u` and `v`, not normalized
+    pub dpdu: Vec3d,
+    pub dpdv: Vec3d,
+
     pub t: f64,
+
+    /// Surface coordinates of the hit point, in [0, 1]
+    pub u: f64,
+    pub v: f64,
+
+    /// Interpolated color of mesh vertices, tints the material
+    pub vertex_color: Option<Color>,
+
     pub front_face: bool,
     pub material: &'mat MaterialConfig,
 }
 
 pub trait Hittable {
-    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord>;
-    fn bounding_box(&self) -> Cow<Aabb>;
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>>;
+    fn bounding_box(&self) -> Cow<'_, Aabb>;
+
+    /// Like `hit`, but adds the work done to the traversal counters of the current thread
+    ///
+    /// Only used for statistics, so normal renders don't pay for counting. Primitives count as a
+    /// single test, objects made of other objects pass the call on to them instead.
+    fn hit_counted(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        acceleration::count_primitive();
+        self.hit(ray, t_min, t_max)
+    }
 }
 
+/// Distance along the geometric normal by which new rays start off the surface
+const RAY_OFFSET: f64 = 1e-6;
+
 impl<'mat> HitRecord<'mat> {
+    /// Turn the normals against the ray, `shading_normal` must be set to the outward facing one before
     pub fn set_normal_face(&mut self, ray: &Ray, outward_normal: &Vec3d) {
         self.front_face = ray.direction.dot(outward_normal) < 0.0;
-        self.normal = if self.front_face {
-            *outward_normal
+        if self.front_face {
+            self.normal = *outward_normal;
         } else {
-            -*outward_normal
+            self.normal = -*outward_normal;
+            self.shading_normal = -self.shading_normal;
+        }
+    }
+
+    /// Ray leaving the hit point in `direction`
+    ///
+    /// The origin is moved off the surface along the geometric normal, to the side the ray leaves
+    /// to, so it can't hit the same surface again even if the shading normal disagrees.
+    pub fn spawn_ray(&self, direction: Vec3d, time: f64) -> Ray {
+        let offset = if direction.dot(&self.normal) > 0.0 {
+            RAY_OFFSET
+        } else {
+            -RAY_OFFSET
         };
+
+        Ray {
+            origin: self.point + offset * self.normal,
+            direction,
+            time,
+        }
+    }
+}
+
+#[cfg(test)]
+impl<'mat> HitRecord<'mat> {
+    /// Front face hit on the z = 0 plane facing +z, where the surface coordinates run along x and y
+    pub fn on_xy_plane(material: &'mat MaterialConfig, u: f64, v: f64) -> Self {
+        HitRecord {
+            point: crate::v3d!(u, v, 0.0),
+            normal: crate::v3d!(0.0, 0.0, 1.0),
+            shading_normal: crate::v3d!(0.0, 0.0, 1.0),
+            dpdu: crate::v3d!(1.0, 0.0, 0.0),
+            dpdv: crate::v3d!(0.0, 1.0, 0.0),
+            t: 1.0,
+            u,
+            v,
+            vertex_color: None,
+            front_face: true,
+            material,
+        }
     }
 }
@@ -0,0 +1,192 @@
+use auto_ops::impl_op_ex;
+
+use crate::core::Vec3d;
+use crate::v3d;
+
+/// Row major 4x4 matrix for affine transformations
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Mat4 {
+    pub m: [[f64; 4]; 4],
+}
+
+impl_op_ex!(
+    *#[inline]
+    |a: &Mat4, b: &Mat4| -> Mat4 {
+        let mut m = [[0.0; 4]; 4];
+        for (r, row) in m.iter_mut().enumerate() {
+            for (c, value) in row.iter_mut().enumerate() {
+                *value = (0..4).map(|k| a.m[r][k] * b.m[k][c]).sum();
+            }
+        }
+        Mat4 { m }
+    }
+);
+
+impl Default for Mat4 {
+    fn default() -> Self {
+        Mat4::identity()
+    }
+}
+
+impl Mat4 {
+    pub const fn identity() -> Self {
+        Mat4 {
+            m: [
+                [1.0, 0.0, 0.0, 0.0],
+                [0.0, 1.0, 0.0, 0.0],
+                [0.0, 0.0, 1.0, 0.0],
+                [0.0, 0.0, 0.0, 1.0],
+            ],
+        }
+    }
+
+    pub fn translation(offset: &Vec3d) -> Self {
+        let mut mat = Mat4::identity();
+        mat.m[0][3] = offset.x;
+        mat.m[1][3] = offset.y;
+        mat.m[2][3] = offset.z;
+        mat
+    }
+
+    pub fn scale(factor: &Vec3d) -> Self {
+        let mut mat = Mat4::identity();
+        mat.m[0][0] = factor.x;
+        mat.m[1][1] = factor.y;
+        mat.m[2][2] = factor.z;
+        mat
+    }
+
+    /// Rotation around the x axis by `angle` degrees
+    pub fn rotation_x(angle: f64) -> Self {
+        let (sin, cos) = angle.to_radians().sin_cos();
+        let mut mat = Mat4::identity();
+        mat.m[1][1] = cos;
+        mat.m[1][2] = -sin;
+        mat.m[2][1] = sin;
+        mat.m[2][2] = cos;
+        mat
+    }
+
+    /// Rotation around the y axis by `angle` degrees
+    pub fn rotation_y(angle: f64) -> Self {
+        let (sin, cos) = angle.to_radians().sin_cos();
+        let mut mat = Mat4::identity();
+        mat.m[0][0] = cos;
+        mat.m[0][2] = sin;
+        mat.m[2][0] = -sin;
+        mat.m[2][2] = cos;
+        mat
+    }
+
+    /// Rotation around the z axis by `angle` degrees
+    pub fn rotation_z(angle: f64) -> Self {
+        let (sin, cos) = angle.to_radians().sin_cos();
+        let mut mat = Mat4::identity();
+        mat.m[0][0] = cos;
+        mat.m[0][1] = -sin;
+        mat.m[1][0] = sin;
+        mat.m[1][1] = cos;
+        mat
+    }
+
+    pub fn transpose(&self) -> Self {
+        let mut m = [[0.0; 4]; 4];
+        for (r, row) in m.iter_mut().enumerate() {
+            for (c, value) in row.iter_mut().enumerate() {
+                *value = self.m[c][r];
+            }
+        }
+        Mat4 { m }
+    }
+
+    /// Inverse of the matrix using Gauss-Jordan elimination, `None` if the matrix is singular
+    pub fn inverse(&self) -> Option<Self> {
+        let mut a = self.m;
+        let mut inv = Mat4::identity().m;
+
+        for col in 0..4 {
+            // Partial pivoting for numerical stability
+            let pivot = (col..4).max_by(|&i, &j| a[i][col].abs().total_cmp(&a[j][col].abs()))?;
+            if a[pivot][col].abs() < 1e-12 {
+                return None;
+            }
+
+            a.swap(col, pivot);
+            inv.swap(col, pivot);
+
+            let factor = 1.0 / a[col][col];
+            for c in 0..4 {
+                a[col][c] *= factor;
+                inv[col][c] *= factor;
+            }
+
+            for row in 0..4 {
+                if row != col {
+                    let factor = a[row][col];
+                    for c in 0..4 {
+                        a[row][c] -= factor * a[col][c];
+                        inv[row][c] -= factor * inv[col][c];
+                    }
+                }
+            }
+        }
+
+        Some(Mat4 { m: inv })
+    }
+
+    /// Apply the full affine transformation to a point
+    #[inline]
+    pub fn transform_point(&self, p: &Vec3d) -> Vec3d {
+        let m = &self.m;
+        v3d!(
+            m[0][0] * p.x + m[0][1] * p.y + m[0][2] * p.z + m[0][3],
+            m[1][0] * p.x + m[1][1] * p.y + m[1][2] * p.z + m[1][3],
+            m[2][0] * p.x + m[2][1] * p.y + m[2][2] * p.z + m[2][3]
+        )
+    }
+
+    /// Apply only the linear part (no translation) to a direction
+    #[inline]
+    pub fn transform_vector(&self, v: &Vec3d) -> Vec3d {
+        let m = &self.m;
+        v3d!(
+            m[0][0] * v.x + m[0][1] * v.y + m[0][2] * v.z,
+            m[1][0] * v.x + m[1][1] * v.y + m[1][2] * v.z,
+            m[2][0] * v.x + m[2][1] * v.y + m[2][2] * v.z
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::*;
+
+    #[test]
+    fn test_inverse() {
+        let mat = Mat4::translation(&v3d!(1.0, -2.0, 3.0))
+            * Mat4::rotation_y(30.0)
+            * Mat4::rotation_x(-45.0)
+            * Mat4::scale(&v3d!(2.0, 0.5, 1.5));
+        let product = mat * mat.inverse().unwrap();
+
+        for r in 0..4 {
+            for c in 0..4 {
+                assert_relative_eq!(product.m[r][c], Mat4::identity().m[r][c], epsilon = 1e-12);
+            }
+        }
+    }
+
+    #[test]
+    fn test_singular() {
+        assert!(Mat4::scale(&v3d!(1.0, 0.0, 1.0)).inverse().is_none());
+    }
+
+    #[test]
+    fn test_rotation() {
+        let p = Mat4::rotation_z(90.0).transform_point(&v3d!(1.0, 0.0, 0.0));
+
+        assert_relative_eq!(p.x, 0.0, epsilon = 1e-12);
+        assert_relative_eq!(p.y, 1.0, epsilon = 1e-12);
+    }
+}
@@ -1,9 +1,15 @@
 pub mod color;
 mod hittable;
+mod matrix;
 mod ray;
+mod tagged;
+mod transform;
 mod vec3d;
 
 pub use color::Color;
 pub use hittable::*;
+pub use matrix::*;
 pub use ray::*;
+pub use tagged::*;
+pub use transform::*;
 pub use vec3d::*;
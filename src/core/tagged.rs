@@ -0,0 +1,82 @@
+use std::{fmt, marker::PhantomData};
+
+use serde::de::{
+    self,
+    value::{MapAccessDeserializer, SeqAccessDeserializer, StrDeserializer},
+    Deserialize, DeserializeSeed, Deserializer, MapAccess, SeqAccess, Visitor,
+};
+
+/// Externally tagged enum which also accepts one of its variants without the tag
+///
+/// Older scene files wrote some values before they became a variant, e.g. bare spheres in the
+/// object list or plain colors as textures.
+pub trait TaggedOr<'de>: Sized {
+    /// Value written without a tag
+    type Untagged: de::Deserialize<'de> + Into<Self>;
+
+    /// The derived deserializer of the tagged form
+    fn deserialize_tagged<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error>;
+}
+
+/// Deserialize `T` in its tagged form, or as its untagged variant if the first key isn't a tag
+///
+/// Only the first key decides which form is read, so errors inside the value are reported as
+/// they are instead of as a mismatch of all variants.
+pub fn deserialize_tagged_or<'de, T: TaggedOr<'de>, D: Deserializer<'de>>(deserializer: D) -> Result<T, D::Error> {
+    deserializer.deserialize_any(TaggedOrVisitor(PhantomData))
+}
+
+struct TaggedOrVisitor<T>(PhantomData<T>);
+
+impl<'de, T: TaggedOr<'de>> Visitor<'de> for TaggedOrVisitor<T> {
+    type Value = T;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a map with a single tag or an untagged value")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, seq: A) -> Result<T, A::Error> {
+        T::Untagged::deserialize(SeqAccessDeserializer::new(seq)).map(Into::into)
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<T, A::Error> {
+        let key = map.next_key::<String>()?;
+        let mut map = PeekedMap { key, map };
+        if map.key.is_none() {
+            return T::Untagged::deserialize(MapAccessDeserializer::new(map)).map(Into::into);
+        }
+
+        match T::deserialize_tagged(MapAccessDeserializer::new(&mut map)) {
+            // The first key is still there if it isn't a tag
+            Err(tagged) if map.key.is_some() => T::Untagged::deserialize(MapAccessDeserializer::new(map))
+                .map(Into::into)
+                .map_err(|untagged| de::Error::custom(format_args!("{tagged}, or without a tag: {untagged}"))),
+            result => result,
+        }
+    }
+}
+
+/// Map of which the first key was already read
+struct PeekedMap<A> {
+    key: Option<String>,
+    map: A,
+}
+
+impl<'de, A: MapAccess<'de>> MapAccess<'de> for PeekedMap<A> {
+    type Error = A::Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>, A::Error> {
+        let Some(key) = &self.key else {
+            return self.map.next_key_seed(seed);
+        };
+
+        // Kept if it is rejected, so it can be read again as another type
+        let value = seed.deserialize(StrDeserializer::new(key))?;
+        self.key = None;
+        Ok(Some(value))
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, A::Error> {
+        self.map.next_value_seed(seed)
+    }
+}
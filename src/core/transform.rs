@@ -0,0 +1,125 @@
+use crate::core::{Mat4, Vec3d};
+use crate::v3d;
+
+/// Placement of an object in the scene file
+///
+/// Applied in the order scale, rotation (x, then y, then z axis) and translation.
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct TransformConfig {
+    pub translate: Vec3d,
+
+    /// Rotation angles around the x, y and z axis in degrees
+    pub rotate: Vec3d,
+
+    pub scale: Vec3d,
+}
+
+impl Default for TransformConfig {
+    fn default() -> Self {
+        TransformConfig {
+            translate: v3d!(0.0, 0.0, 0.0),
+            rotate: v3d!(0.0, 0.0, 0.0),
+            scale: v3d!(1.0, 1.0, 1.0),
+        }
+    }
+}
+
+impl TransformConfig {
+    pub fn matrix(&self) -> Mat4 {
+        Mat4::translation(&self.translate)
+            * Mat4::rotation_z(self.rotate.z)
+            * Mat4::rotation_y(self.rotate.y)
+            * Mat4::rotation_x(self.rotate.x)
+            * Mat4::scale(&self.scale)
+    }
+
+    /// Split an affine matrix into translation, rotation and scale
+    ///
+    /// `None` if the matrix can't be written that way, e.g. because it shears or scales by zero.
+    pub fn from_matrix(matrix: &Mat4) -> Option<Self> {
+        let m = &matrix.m;
+        let column = |c: usize| v3d!(m[0][c], m[1][c], m[2][c]);
+
+        // A mirroring matrix is taken as rotation with a negative scale in x
+        let det = column(0).dot(&column(1).cross(&column(2)));
+        let scale = v3d!(
+            column(0).length() * det.signum(),
+            column(1).length(),
+            column(2).length()
+        );
+        let r = |row: usize, c: usize| {
+            m[row][c]
+                / match c {
+                    0 => scale.x,
+                    1 => scale.y,
+                    _ => scale.z,
+                }
+        };
+
+        // Angles of the rotation around z, y and x in this order, the x angle alone if y is at +-90 degrees
+        let y = (-r(2, 0)).clamp(-1.0, 1.0).asin();
+        let (x, z) = if y.cos() > 1e-6 {
+            (r(2, 1).atan2(r(2, 2)), r(1, 0).atan2(r(0, 0)))
+        } else {
+            ((-r(1, 2)).atan2(r(1, 1)), 0.0)
+        };
+
+        let transform = TransformConfig {
+            translate: column(3),
+            rotate: v3d!(x.to_degrees(), y.to_degrees(), z.to_degrees()),
+            scale,
+        };
+
+        // Matrices from files are often single precision
+        let size = scale.length();
+        let same = transform
+            .matrix()
+            .m
+            .iter()
+            .flatten()
+            .zip(m.iter().flatten())
+            .all(|(a, b)| (a - b).abs() <= 1e-5 * size.max(1.0));
+        same.then_some(transform)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::*;
+
+    #[test]
+    fn test_from_matrix() {
+        let transform = TransformConfig {
+            translate: v3d!(1.0, -2.0, 3.0),
+            rotate: v3d!(30.0, -60.0, 120.0),
+            scale: v3d!(-0.5, 2.0, 1.5),
+        };
+
+        let split = TransformConfig::from_matrix(&transform.matrix()).unwrap();
+        for (a, b) in split
+            .matrix()
+            .m
+            .iter()
+            .flatten()
+            .zip(transform.matrix().m.iter().flatten())
+        {
+            assert_relative_eq!(a, b, epsilon = 1e-9);
+        }
+        assert_relative_eq!(split.rotate.y, -60.0, epsilon = 1e-9);
+
+        // Rotated straight up, only the difference of the x and z angles is known
+        let gimbal = TransformConfig {
+            rotate: v3d!(20.0, 90.0, 10.0),
+            ..TransformConfig::default()
+        };
+        let split = TransformConfig::from_matrix(&gimbal.matrix()).unwrap();
+        assert_relative_eq!(split.rotate.y, 90.0, epsilon = 1e-6);
+
+        let mut shear = Mat4::identity();
+        shear.m[0][1] = 0.5;
+        assert!(TransformConfig::from_matrix(&shear).is_none());
+        assert!(TransformConfig::from_matrix(&Mat4::scale(&v3d!(1.0, 0.0, 1.0))).is_none());
+    }
+}
@@ -170,6 +170,17 @@ impl Vec3d {
         self.x * rhs.x + self.y * rhs.y + self.z * rhs.z
     }
 
+    /// Component along axis `n` (0 = x, 1 = y, 2 = z)
+    #[inline]
+    pub const fn axis(&self, n: usize) -> f64 {
+        match n {
+            0 => self.x,
+            1 => self.y,
+            2 => self.z,
+            _ => panic!("Axis is not supported"),
+        }
+    }
+
     /// Return true if the vector is close to zero in all dimensions.
     #[inline]
     pub fn is_near_zero(&self) -> bool {
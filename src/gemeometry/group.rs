@@ -0,0 +1,156 @@
+use std::{borrow::Cow, sync::OnceLock};
+
+use super::world::{hit_all, union_bounds};
+use crate::{
+    acceleration::{self, Aabb, Bvh},
+    core::{HitRecord, Hittable, Ray},
+    gemeometry::ObjectConfig,
+};
+
+/// Several objects used like a single one, e.g. to place them together with an `Instance`
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+pub struct Group {
+    /// Moved into `bvh` by `build` if the group uses one
+    objects: Vec<ObjectConfig>,
+
+    /// Use a bounding volume hierarchy of its own instead of testing all objects of the group
+    #[serde(default)]
+    use_bvh: bool,
+
+    /// Built by `build` once all meshes are loaded, all objects are tested until then
+    #[serde(skip)]
+    bvh: Option<Bvh<ObjectConfig>>,
+
+    #[serde(skip)]
+    bounding_box: OnceLock<Aabb>,
+}
+
+impl Group {
+    pub fn objects(&self) -> &[ObjectConfig] {
+        match &self.bvh {
+            Some(bvh) => bvh.primitives(),
+            None => &self.objects,
+        }
+    }
+
+    /// Build the hierarchies of this and all nested groups, the meshes must already be loaded
+    pub fn build(&mut self) {
+        self.objects.iter_mut().for_each(ObjectConfig::build);
+
+        if self.use_bvh && !self.objects.is_empty() {
+            self.bvh = Some(Bvh::build(std::mem::take(&mut self.objects)));
+        }
+        self.bounding_box = OnceLock::new();
+    }
+
+    /// Move the objects to where they are in the next frame of an animation
+    pub fn advance_frame(&mut self) {
+        match &mut self.bvh {
+            Some(bvh) => {
+                bvh.primitives_mut().iter_mut().for_each(ObjectConfig::advance_frame);
+                bvh.refit(Some(acceleration::MAX_BVH_DEGRADATION));
+            }
+            None => self.objects.iter_mut().for_each(ObjectConfig::advance_frame),
+        }
+        self.bounding_box = OnceLock::new();
+    }
+}
+
+impl Hittable for Group {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        match &self.bvh {
+            Some(bvh) => bvh.hit(ray, t_min, t_max),
+            None => hit_all::<false>(&self.objects, ray, t_min, t_max),
+        }
+    }
+
+    fn hit_counted(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        match &self.bvh {
+            Some(bvh) => bvh.hit_counted(ray, t_min, t_max),
+            None => hit_all::<true>(&self.objects, ray, t_min, t_max),
+        }
+    }
+
+    fn bounding_box(&self) -> Cow<'_, Aabb> {
+        Cow::Borrowed(self.bounding_box.get_or_init(|| union_bounds(self.objects())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{color, gemeometry::Sphere, material::Lambertian, material::MaterialConfig, texture::Texture, v3d};
+    use approx::*;
+
+    fn test_group(use_bvh: bool) -> Group {
+        let material = MaterialConfig::Lambertian(Lambertian {
+            albedo: Texture::Solid(color!(0.5, 0.5, 0.5)),
+        });
+
+        let mut group = Group {
+            objects: vec![
+                ObjectConfig::Sphere(Sphere::new(v3d!(-2.0, 0.0, -5.0), 1.0, material.clone())),
+                ObjectConfig::Sphere(Sphere::new(v3d!(2.0, 0.0, -5.0), 1.0, material)),
+            ],
+            use_bvh,
+            bvh: None,
+            bounding_box: OnceLock::new(),
+        };
+        group.build();
+        group
+    }
+
+    #[test]
+    fn test_bounding_box() {
+        let group = test_group(false);
+        let bbox = group.bounding_box();
+
+        assert_relative_eq!(bbox.axis(0).start, -3.0);
+        assert_relative_eq!(bbox.axis(0).end, 3.0);
+        assert_relative_eq!(bbox.axis(2).start, -6.0);
+    }
+
+    #[test]
+    fn test_hit_with_and_without_bvh() {
+        let ray = Ray {
+            origin: v3d!(2.0, 0.0, 0.0),
+            direction: v3d!(0.0, 0.0, -1.0),
+            time: 0.0,
+        };
+
+        for use_bvh in [false, true] {
+            let group = test_group(use_bvh);
+            assert_eq!(group.bvh.is_some(), use_bvh);
+            assert_eq!(group.objects().len(), 2);
+
+            let hit = group.hit(&ray, 0.0, f64::INFINITY).unwrap();
+            assert_relative_eq!(hit.t, 4.0);
+        }
+    }
+
+    #[test]
+    fn test_untagged_sphere() {
+        let sphere = serde_json::json!({
+            "origin": { "x": 0.0, "y": 0.0, "z": -1.0 },
+            "radius": 0.5,
+            "material": { "Lambertian": { "albedo": { "x": 0.7, "y": 0.3, "z": 0.3 } } }
+        });
+        let group: Group = serde_json::from_value(serde_json::json!({ "objects": [sphere] })).unwrap();
+
+        assert!(matches!(group.objects(), [ObjectConfig::Sphere(sphere)] if sphere.radius == 0.5));
+    }
+
+    #[test]
+    fn test_empty_group() {
+        let mut group: Group = serde_json::from_value(serde_json::json!({ "objects": [], "use_bvh": true })).unwrap();
+        group.build();
+
+        let ray = Ray {
+            origin: v3d!(0.0, 0.0, 1.0),
+            direction: v3d!(0.0, 0.0, -1.0),
+            time: 0.0,
+        };
+        assert!(group.hit(&ray, 0.0, f64::INFINITY).is_none());
+        assert_eq!(group.bounding_box().surface_area(), 0.0);
+    }
+}
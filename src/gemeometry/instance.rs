@@ -0,0 +1,226 @@
+use std::{borrow::Cow, sync::OnceLock};
+
+use crate::{
+    acceleration::{stats, Aabb},
+    core::{HitRecord, Hittable, Mat4, Ray, TransformConfig},
+    gemeometry::ObjectConfig,
+    v3d,
+};
+
+/// Places any object in the scene with an affine transformation
+///
+/// Rays are transformed into the object space of the wrapped object, hit points and normals
+/// are transformed back into world space.
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+#[serde(try_from = "InstanceFile")]
+pub struct Instance {
+    pub object: Box<ObjectConfig>,
+    pub transform: TransformConfig,
+
+    /// Boxed to keep the size of `ObjectConfig` small
+    #[serde(skip)]
+    transformation: Box<Transformation>,
+
+    #[serde(skip)]
+    bounding_box: OnceLock<Aabb>,
+}
+
+/// `Instance` as written in the scene file
+#[derive(serde::Deserialize)]
+struct InstanceFile {
+    object: Box<ObjectConfig>,
+    transform: TransformConfig,
+}
+
+/// Matrices to move rays, hits and bounding boxes between world and object space
+#[derive(Clone)]
+pub struct Transformation {
+    object_to_world: Mat4,
+    world_to_object: Mat4,
+
+    /// Inverse transpose of `object_to_world`, for transforming normals
+    normal_to_world: Mat4,
+}
+
+impl Transformation {
+    /// Fails for transforms which can't be undone, i.e. scaling by zero
+    pub fn new(transform: &TransformConfig) -> Result<Self, String> {
+        let object_to_world = transform.matrix();
+        let Some(world_to_object) = object_to_world.inverse() else {
+            let scale = &transform.scale;
+            return Err(format!(
+                "Transform with scale ({}, {}, {}) is not invertible",
+                scale.x, scale.y, scale.z
+            ));
+        };
+
+        Ok(Transformation {
+            object_to_world,
+            world_to_object,
+            normal_to_world: world_to_object.transpose(),
+        })
+    }
+
+    /// The ray in object space, the direction is not normalized so t is the same in both spaces
+    #[inline]
+    pub fn ray_to_object(&self, ray: &Ray) -> Ray {
+        Ray {
+            origin: self.world_to_object.transform_point(&ray.origin),
+            direction: self.world_to_object.transform_vector(&ray.direction),
+            time: ray.time,
+        }
+    }
+
+    #[inline]
+    pub fn hit_to_world(&self, rec: &mut HitRecord) {
+        rec.point = self.object_to_world.transform_point(&rec.point);
+        rec.normal = self.normal_to_world.transform_vector(&rec.normal).unit_vector();
+        rec.shading_normal = self.normal_to_world.transform_vector(&rec.shading_normal).unit_vector();
+        rec.dpdu = self.object_to_world.transform_vector(&rec.dpdu);
+        rec.dpdv = self.object_to_world.transform_vector(&rec.dpdv);
+    }
+
+    /// Bounding box of all transformed corners of `object_box`
+    pub fn bounding_box_to_world(&self, object_box: &Aabb) -> Aabb {
+        (0..8)
+            .map(|corner| {
+                let pick = |axis: usize| {
+                    let range = object_box.axis(axis);
+                    if corner & (1 << axis) == 0 {
+                        range.start
+                    } else {
+                        range.end
+                    }
+                };
+                let p = self.object_to_world.transform_point(&v3d!(pick(0), pick(1), pick(2)));
+                Aabb::from_points(&p, &p)
+            })
+            .reduce(|a, b| Aabb::from_aabb(&a, &b))
+            .unwrap()
+    }
+}
+
+impl TryFrom<InstanceFile> for Instance {
+    type Error = String;
+
+    fn try_from(file: InstanceFile) -> Result<Self, String> {
+        Instance::new(file.object, file.transform)
+    }
+}
+
+impl Instance {
+    /// Fails if the transform isn't invertible
+    pub fn new(object: Box<ObjectConfig>, transform: TransformConfig) -> Result<Self, String> {
+        Ok(Instance {
+            transformation: Box::new(
+                Transformation::new(&transform).map_err(|err| format!("Invalid instance: {err}"))?,
+            ),
+            object,
+            transform,
+            bounding_box: OnceLock::new(),
+        })
+    }
+
+    #[inline]
+    fn transformation(&self) -> &Transformation {
+        &self.transformation
+    }
+
+    /// Hit of the wrapped object in world space, counting the work done if `COUNT` is set
+    #[inline]
+    fn hit_object<const COUNT: bool>(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        let transformation = self.transformation();
+
+        let mut rec = stats::hit::<COUNT>(self.object.as_ref(), &transformation.ray_to_object(ray), t_min, t_max)?;
+        transformation.hit_to_world(&mut rec);
+
+        Some(rec)
+    }
+
+    /// Move the wrapped object to where it is in the next frame of an animation
+    pub fn advance_frame(&mut self) {
+        self.object.advance_frame();
+        self.bounding_box = OnceLock::new();
+    }
+}
+
+impl Hittable for Instance {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        self.hit_object::<false>(ray, t_min, t_max)
+    }
+
+    fn hit_counted(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        self.hit_object::<true>(ray, t_min, t_max)
+    }
+
+    fn bounding_box(&self) -> Cow<'_, Aabb> {
+        Cow::Borrowed(
+            self.bounding_box
+                .get_or_init(|| self.transformation().bounding_box_to_world(&self.object.bounding_box())),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{color, gemeometry::Sphere, material::Lambertian, material::MaterialConfig, texture::Texture};
+    use approx::*;
+
+    fn test_instance() -> Instance {
+        let sphere = Sphere::new(
+            v3d!(0.0, 0.0, 0.0),
+            1.0,
+            MaterialConfig::Lambertian(Lambertian {
+                albedo: Texture::Solid(color!(0.5, 0.5, 0.5)),
+            }),
+        );
+
+        Instance::new(
+            Box::new(ObjectConfig::Sphere(sphere)),
+            TransformConfig {
+                translate: v3d!(0.0, 0.0, -5.0),
+                rotate: v3d!(0.0, 0.0, 45.0),
+                scale: v3d!(2.0, 1.0, 1.0),
+            },
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_hit_transformed() {
+        let instance = test_instance();
+        let ray = Ray {
+            origin: v3d!(0.0, 0.0, 0.0),
+            direction: v3d!(0.0, 0.0, -1.0),
+            time: 0.0,
+        };
+
+        let hit = instance.hit(&ray, 0.0, f64::INFINITY).unwrap();
+        assert_relative_eq!(hit.t, 4.0, epsilon = 1e-9);
+        assert_relative_eq!(hit.point.z, -4.0, epsilon = 1e-9);
+        assert_relative_eq!(hit.normal.z, 1.0, epsilon = 1e-9);
+    }
+
+    #[test]
+    fn test_bounding_box() {
+        let instance = test_instance();
+        let bbox = instance.bounding_box();
+
+        // Rotated by 45 degrees, the box of the stretched sphere grows in x and y
+        let extent = (2.0f64 + 1.0) / 2.0f64.sqrt();
+        assert_relative_eq!(bbox.axis(0).end, extent, epsilon = 1e-9);
+        assert_relative_eq!(bbox.axis(1).start, -extent, epsilon = 1e-9);
+        assert_relative_eq!(bbox.axis(2).start, -6.0, epsilon = 1e-9);
+    }
+    #[test]
+    fn test_singular_transform_fails_to_load() {
+        let mut json = serde_json::to_value(test_instance()).unwrap();
+        json["transform"]["scale"]["y"] = serde_json::json!(0.0);
+
+        let err = serde_json::from_value::<Instance>(json).err().unwrap();
+        assert!(err
+            .to_string()
+            .starts_with("Invalid instance: Transform with scale (2, 0, 1) is not invertible"));
+    }
+}
@@ -0,0 +1,227 @@
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::Arc,
+    sync::OnceLock,
+};
+
+use crate::{
+    acceleration::{stats, Aabb, Bvh},
+    core::{HitRecord, Hittable, Mat4, Ray, TransformConfig},
+    gemeometry::{ObjectConfig, Transformation, Triangle},
+    loader::{self, MeshFace},
+    material::MaterialConfig,
+};
+
+/// Triangle mesh loaded from a file
+///
+/// The triangles are kept in object space in their own bounding volume hierarchy, which can be
+/// shared by all meshes referencing the same file (see `MeshCache`). Each mesh only adds its
+/// transform and material on top of the shared geometry.
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+pub struct Mesh {
+    /// Path to the mesh file, Wavefront `.obj` or Stanford `.ply`
+    ///
+    /// Empty for meshes built with `from_faces`, it is then left out when saving the scene.
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub file_path: String,
+
+    /// Where the faces passed to `from_faces` came from, for error messages
+    #[serde(skip)]
+    source: String,
+
+    pub material: MaterialConfig,
+
+    #[serde(default, deserialize_with = "deserialize_transform")]
+    pub transform: TransformConfig,
+
+    /// Interpolate the vertex normals from the file, if there are any
+    #[serde(default = "default_smooth_normals")]
+    pub smooth_normals: bool,
+
+    #[serde(skip)]
+    geometry: OnceLock<Arc<Bvh<Triangle>>>,
+
+    /// `None` for the identity transform, boxed to keep the size of `ObjectConfig` small
+    #[serde(skip)]
+    transformation: OnceLock<Option<Box<Transformation>>>,
+
+    #[serde(skip)]
+    bounding_box: OnceLock<Aabb>,
+}
+
+/// Geometry of all loaded mesh files
+///
+/// Meshes referencing the same file share a single copy of the triangles and their BVH, so
+/// placing a mesh many times costs little more memory than placing it once.
+#[derive(Default)]
+pub struct MeshCache {
+    geometry: HashMap<(String, bool), Arc<Bvh<Triangle>>>,
+
+    /// Directory to save the built BVHs in, to load them instead of building them again
+    bvh_cache: Option<PathBuf>,
+}
+
+fn default_smooth_normals() -> bool {
+    true
+}
+
+/// Transform read from the scene file, which must be invertible
+fn deserialize_transform<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<TransformConfig, D::Error> {
+    let transform = <TransformConfig as serde::Deserialize>::deserialize(deserializer)?;
+    Transformation::new(&transform).map_err(|err| serde::de::Error::custom(format!("Invalid mesh: {err}")))?;
+    Ok(transform)
+}
+
+impl Mesh {
+    /// Mesh from already loaded faces, e.g. from a scene file containing several meshes
+    ///
+    /// `source` is only used to identify the mesh in error messages.
+    pub fn from_faces(source: String, faces: &[MeshFace], material: MaterialConfig) -> Self {
+        let mesh = Mesh {
+            file_path: String::new(),
+            source,
+            material,
+            transform: TransformConfig::default(),
+            smooth_normals: true,
+            geometry: OnceLock::new(),
+            transformation: OnceLock::new(),
+            bounding_box: OnceLock::new(),
+        };
+
+        _ = mesh.geometry.set(Arc::new(mesh.build_geometry(faces, None)));
+        mesh
+    }
+
+    /// The acceleration structure over all triangles, loads the file if it wasn't shared before
+    fn geometry(&self) -> &Bvh<Triangle> {
+        self.geometry.get_or_init(|| Arc::new(self.load_geometry(None)))
+    }
+
+    fn load_geometry(&self, bvh_cache: Option<&Path>) -> Bvh<Triangle> {
+        let faces = loader::load_mesh(&self.file_path).unwrap_or_else(|err| panic!("{err}"));
+        self.build_geometry(&faces, bvh_cache)
+    }
+
+    fn build_geometry(&self, faces: &[MeshFace], bvh_cache: Option<&Path>) -> Bvh<Triangle> {
+        if faces.is_empty() {
+            let source = if self.file_path.is_empty() {
+                &self.source
+            } else {
+                &self.file_path
+            };
+            panic!("Mesh '{source}' contains no triangles");
+        }
+
+        // Only used for direct hits of the triangles, the mesh replaces it with its own material
+        let material = Arc::new(self.material.clone());
+
+        let triangles = faces
+            .iter()
+            .map(|[a, b, c]| Triangle {
+                vertices: [a.position, b.position, c.position],
+                normals: match (self.smooth_normals, a.normal, b.normal, c.normal) {
+                    (true, Some(na), Some(nb), Some(nc)) => Some([na, nb, nc].map(|n| n.unit_vector())),
+                    _ => None,
+                },
+                uvs: match (a.uv, b.uv, c.uv) {
+                    (Some(ta), Some(tb), Some(tc)) => Some([ta, tb, tc]),
+                    _ => None,
+                },
+                colors: match (a.color, b.color, c.color) {
+                    (Some(ca), Some(cb), Some(cc)) => Some([ca, cb, cc]),
+                    _ => None,
+                },
+                material: material.clone(),
+            })
+            .collect::<Vec<_>>();
+
+        Bvh::build_cached(triangles, bvh_cache)
+    }
+
+    /// Closest hit of the triangles, counting the work done if `COUNT` is set
+    #[inline]
+    fn hit_geometry<const COUNT: bool>(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        let mut rec = match self.transformation() {
+            Some(transformation) => {
+                let object_ray = transformation.ray_to_object(ray);
+                let mut rec = stats::hit::<COUNT>(self.geometry(), &object_ray, t_min, t_max)?;
+                transformation.hit_to_world(&mut rec);
+                rec
+            }
+            None => stats::hit::<COUNT>(self.geometry(), ray, t_min, t_max)?,
+        };
+
+        rec.material = &self.material;
+        Some(rec)
+    }
+
+    /// Whether both meshes use the same copy of the triangles
+    #[cfg(test)]
+    pub fn shares_geometry(&self, other: &Mesh) -> bool {
+        Arc::ptr_eq(self.geometry.get().unwrap(), other.geometry.get().unwrap())
+    }
+
+    #[inline]
+    fn transformation(&self) -> Option<&Transformation> {
+        self.transformation
+            .get_or_init(|| {
+                let identity = self.transform.matrix() == Mat4::identity();
+                // Checked when reading the scene, meshes from `from_faces` have the identity
+                (!identity)
+                    .then(|| Box::new(Transformation::new(&self.transform).unwrap_or_else(|err| panic!("{err}"))))
+            })
+            .as_deref()
+    }
+}
+
+impl Hittable for Mesh {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        self.hit_geometry::<false>(ray, t_min, t_max)
+    }
+
+    fn hit_counted(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        self.hit_geometry::<true>(ray, t_min, t_max)
+    }
+
+    fn bounding_box(&self) -> Cow<'_, Aabb> {
+        Cow::Borrowed(self.bounding_box.get_or_init(|| match self.transformation() {
+            Some(transformation) => transformation.bounding_box_to_world(&self.geometry().bounding_box()),
+            None => self.geometry().bounding_box().into_owned(),
+        }))
+    }
+}
+
+impl MeshCache {
+    pub fn new(bvh_cache: Option<PathBuf>) -> Self {
+        MeshCache {
+            geometry: HashMap::new(),
+            bvh_cache,
+        }
+    }
+
+    /// Load the geometry of all meshes in `objects`, reusing already loaded files
+    pub fn load_all(&mut self, objects: &[ObjectConfig]) {
+        for object in objects {
+            self.load_object(object);
+        }
+    }
+
+    fn load_object(&mut self, object: &ObjectConfig) {
+        match object {
+            ObjectConfig::Mesh(mesh) if mesh.geometry.get().is_none() => {
+                let key = (mesh.file_path.clone(), mesh.smooth_normals);
+                let geometry = self
+                    .geometry
+                    .entry(key)
+                    .or_insert_with(|| Arc::new(mesh.load_geometry(self.bvh_cache.as_deref())));
+
+                _ = mesh.geometry.set(geometry.clone());
+            }
+            ObjectConfig::Instance(instance) => self.load_object(&instance.object),
+            ObjectConfig::Group(group) => self.load_all(group.objects()),
+            _ => (),
+        }
+    }
+}
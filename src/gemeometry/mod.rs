@@ -1,5 +1,152 @@
+mod group;
+mod instance;
+mod mesh;
+mod quad;
 mod sphere;
+mod triangle;
 mod world;
 
+pub use group::Group;
+pub use instance::{Instance, Transformation};
+pub use mesh::{Mesh, MeshCache};
+pub use quad::Quad;
 pub use sphere::Sphere;
-pub use world::*;
+pub use triangle::Triangle;
+pub use world::ObjectList;
+
+use std::borrow::Cow;
+
+use crate::{
+    acceleration::Aabb,
+    core::{self, HitRecord, Hittable, Ray, TaggedOr},
+};
+
+/// Any object of the scene
+///
+/// Scene files from before there were other objects list bare spheres without the `Sphere` tag,
+/// they are still accepted.
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+#[serde(remote = "Self")]
+pub enum ObjectConfig {
+    Sphere(Sphere),
+    Quad(Quad),
+    Mesh(Mesh),
+    Instance(Instance),
+    Group(Group),
+}
+
+impl<'de> TaggedOr<'de> for ObjectConfig {
+    type Untagged = Sphere;
+
+    fn deserialize_tagged<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        ObjectConfig::deserialize(deserializer)
+    }
+}
+
+impl From<Sphere> for ObjectConfig {
+    fn from(sphere: Sphere) -> Self {
+        ObjectConfig::Sphere(sphere)
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for ObjectConfig {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        core::deserialize_tagged_or(deserializer)
+    }
+}
+
+impl serde::Serialize for ObjectConfig {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        ObjectConfig::serialize(self, serializer)
+    }
+}
+
+impl ObjectConfig {
+    /// Build the acceleration structures of nested groups, the meshes must already be loaded
+    pub fn build(&mut self) {
+        match self {
+            ObjectConfig::Instance(o) => o.object.build(),
+            ObjectConfig::Group(o) => o.build(),
+            _ => (),
+        }
+    }
+
+    /// Move the object to where it is in the next frame of an animation
+    pub fn advance_frame(&mut self) {
+        // Only spheres move, instances and groups pass it on to the objects they contain
+        match self {
+            ObjectConfig::Sphere(o) => o.advance_frame(),
+            ObjectConfig::Instance(o) => o.advance_frame(),
+            ObjectConfig::Group(o) => o.advance_frame(),
+            _ => (),
+        }
+    }
+}
+
+impl Hittable for ObjectConfig {
+    #[inline]
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        match &self {
+            ObjectConfig::Sphere(o) => o.hit(ray, t_min, t_max),
+            ObjectConfig::Quad(o) => o.hit(ray, t_min, t_max),
+            ObjectConfig::Mesh(o) => o.hit(ray, t_min, t_max),
+            ObjectConfig::Instance(o) => o.hit(ray, t_min, t_max),
+            ObjectConfig::Group(o) => o.hit(ray, t_min, t_max),
+        }
+    }
+
+    fn hit_counted(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        match &self {
+            ObjectConfig::Sphere(o) => o.hit_counted(ray, t_min, t_max),
+            ObjectConfig::Quad(o) => o.hit_counted(ray, t_min, t_max),
+            ObjectConfig::Mesh(o) => o.hit_counted(ray, t_min, t_max),
+            ObjectConfig::Instance(o) => o.hit_counted(ray, t_min, t_max),
+            ObjectConfig::Group(o) => o.hit_counted(ray, t_min, t_max),
+        }
+    }
+
+    #[inline]
+    fn bounding_box(&self) -> Cow<'_, Aabb> {
+        match &self {
+            ObjectConfig::Sphere(o) => o.bounding_box(),
+            ObjectConfig::Quad(o) => o.bounding_box(),
+            ObjectConfig::Mesh(o) => o.bounding_box(),
+            ObjectConfig::Instance(o) => o.bounding_box(),
+            ObjectConfig::Group(o) => o.bounding_box(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(serde::Deserialize)]
+    struct Objects {
+        objects: Vec<ObjectConfig>,
+    }
+
+    #[test]
+    fn test_deserialize_untagged_spheres() {
+        let sphere = serde_json::json!({
+            "origin": { "x": 0.0, "y": 0.0, "z": -1.0 },
+            "radius": 0.5,
+            "material": { "Lambertian": { "albedo": { "x": 0.7, "y": 0.3, "z": 0.3 } } }
+        });
+        let json = serde_json::json!({ "objects": [sphere.clone(), { "Sphere": sphere }] });
+
+        let objects = serde_json::from_value::<Objects>(json).unwrap().objects;
+        assert_eq!(objects.len(), 2);
+        for object in &objects {
+            assert!(matches!(object, ObjectConfig::Sphere(sphere) if sphere.radius == 0.5));
+        }
+    }
+
+    #[test]
+    fn test_invalid_object_keeps_its_error() {
+        let json = serde_json::json!({ "objects": [{ "Quad": { "corner": { "x": 0.0, "y": 0.0, "z": 0.0 } } }] });
+
+        let err = serde_json::from_value::<Objects>(json).err().unwrap();
+        assert!(err.to_string().contains("missing field"));
+    }
+}
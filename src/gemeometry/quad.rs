@@ -0,0 +1,161 @@
+use std::{borrow::Cow, sync::OnceLock};
+
+use crate::{
+    acceleration::Aabb,
+    core::{HitRecord, Hittable, Ray, Vec3d},
+    material::MaterialConfig,
+};
+
+/// Parallelogram spanned by the two edges `u` and `v` starting at `corner`
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+pub struct Quad {
+    pub corner: Vec3d,
+    pub u: Vec3d,
+    pub v: Vec3d,
+    pub material: MaterialConfig,
+
+    #[serde(skip)]
+    plane: OnceLock<Plane>,
+
+    #[serde(skip)]
+    bounding_box: OnceLock<Aabb>,
+}
+
+/// Values derived from the edges, needed for every intersection test
+#[derive(Clone)]
+struct Plane {
+    normal: Vec3d,
+
+    /// Plane equation constant, `normal.dot(p) = d` for all points `p` on the plane
+    d: f64,
+
+    /// Scaled plane normal to compute the planar coordinates of a hit point
+    w: Vec3d,
+}
+
+impl Quad {
+    #[inline]
+    fn plane(&self) -> &Plane {
+        self.plane.get_or_init(|| {
+            let n = self.u.cross(&self.v);
+            let normal = n.unit_vector();
+
+            Plane {
+                normal,
+                d: normal.dot(&self.corner),
+                w: n / n.length_squared(),
+            }
+        })
+    }
+}
+
+impl Hittable for Quad {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        let plane = self.plane();
+        let denom = plane.normal.dot(&ray.direction);
+
+        // No hit if the ray is parallel to the plane
+        if denom.abs() < 1e-8 {
+            return None;
+        }
+
+        let t = (plane.d - plane.normal.dot(&ray.origin)) / denom;
+        if t <= t_min || t_max <= t {
+            return None;
+        }
+
+        // Determine if the hit point lies within the planar shape using its plane coordinates
+        let point = ray.at(t);
+        let planar_hit = point - self.corner;
+        let alpha = plane.w.dot(&planar_hit.cross(&self.v));
+        let beta = plane.w.dot(&self.u.cross(&planar_hit));
+
+        if !(0.0..=1.0).contains(&alpha) || !(0.0..=1.0).contains(&beta) {
+            return None;
+        }
+
+        let mut rec = HitRecord {
+            t,
+            normal: plane.normal,
+            shading_normal: plane.normal,
+            dpdu: self.u,
+            dpdv: self.v,
+            point,
+            u: alpha,
+            v: beta,
+            vertex_color: None,
+            front_face: false,
+            material: &self.material,
+        };
+
+        rec.set_normal_face(ray, &plane.normal);
+
+        Some(rec)
+    }
+
+    fn bounding_box(&self) -> Cow<'_, Aabb> {
+        Cow::Borrowed(self.bounding_box.get_or_init(|| {
+            let diagonal_a = Aabb::from_points(&self.corner, &(self.corner + self.u + self.v));
+            let diagonal_b = Aabb::from_points(&(self.corner + self.u), &(self.corner + self.v));
+
+            // An axis aligned quad is flat in one dimension, which the bounding box must not be
+            Aabb::from_aabb(&diagonal_a, &diagonal_b).pad(1e-4)
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{color, material::Lambertian, texture::Texture, v3d};
+    use approx::*;
+
+    fn test_quad() -> Quad {
+        Quad {
+            corner: v3d!(-1.0, -1.0, -2.0),
+            u: v3d!(2.0, 0.0, 0.0),
+            v: v3d!(0.0, 2.0, 0.0),
+            material: MaterialConfig::Lambertian(Lambertian {
+                albedo: Texture::Solid(color!(0.5, 0.5, 0.5)),
+            }),
+            plane: OnceLock::new(),
+            bounding_box: OnceLock::new(),
+        }
+    }
+
+    #[test]
+    fn test_hit_inside() {
+        let ray = Ray {
+            origin: v3d!(0.5, 0.5, 0.0),
+            direction: v3d!(0.0, 0.0, -1.0),
+            time: 0.0,
+        };
+
+        let quad = test_quad();
+        let hit = quad.hit(&ray, 0.0, f64::INFINITY).unwrap();
+        assert_relative_eq!(hit.t, 2.0);
+        assert_relative_eq!(hit.normal.z, 1.0);
+        assert!(hit.front_face);
+    }
+
+    #[test]
+    fn test_miss_outside() {
+        let ray = Ray {
+            origin: v3d!(1.5, 0.0, 0.0),
+            direction: v3d!(0.0, 0.0, -1.0),
+            time: 0.0,
+        };
+
+        assert!(test_quad().hit(&ray, 0.0, f64::INFINITY).is_none());
+    }
+
+    #[test]
+    fn test_bounding_box_padded() {
+        let quad = test_quad();
+        let bbox = quad.bounding_box();
+
+        assert!(bbox.axis(2).end - bbox.axis(2).start > 0.0);
+        assert_relative_eq!(bbox.axis(0).start, -1.0);
+        assert_relative_eq!(bbox.axis(1).end, 1.0);
+    }
+}
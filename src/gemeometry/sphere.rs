@@ -1,4 +1,4 @@
-use std::{borrow::Cow, sync::OnceLock};
+use std::{borrow::Cow, f64::consts::PI, sync::OnceLock};
 
 use crate::{
     acceleration::Aabb,
@@ -39,6 +39,14 @@ impl Sphere {
         }
     }
 
+    /// Move to the end of the motion, where the sphere starts in the next frame of an animation
+    pub fn advance_frame(&mut self) {
+        if let Some(moving_vec) = self.moving_vec {
+            self.origin += moving_vec;
+            self.bounding_box = OnceLock::new();
+        }
+    }
+
     #[inline]
     fn position(&self, time: f64) -> Vec3d {
         if let Some(moving_vec) = self.moving_vec {
@@ -50,7 +58,7 @@ impl Sphere {
 }
 
 impl Hittable for Sphere {
-    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
         let oc = ray.origin - self.position(ray.time);
 
         let a = ray.direction.length_squared();
@@ -73,21 +81,30 @@ impl Hittable for Sphere {
         }
 
         let point = ray.at(root);
+        let outward_normal = (point - self.position(ray.time)) / self.radius;
+        let (u, v) = sphere_uv(&outward_normal);
+        let (dpdu, dpdv) = sphere_derivatives(&outward_normal, self.radius);
 
         let mut rec = HitRecord {
             t: root,
-            normal: (point - self.position(ray.time)) / self.radius,
+            normal: outward_normal,
+            shading_normal: outward_normal,
+            dpdu,
+            dpdv,
             point,
+            u,
+            v,
+            vertex_color: None,
             front_face: false,
             material: &self.material,
         };
 
-        rec.set_normal_face(ray, &rec.normal.clone());
+        rec.set_normal_face(ray, &outward_normal);
 
         Some(rec)
     }
 
-    fn bounding_box(&self) -> Cow<crate::acceleration::Aabb> {
+    fn bounding_box(&self) -> Cow<'_, crate::acceleration::Aabb> {
         Cow::Borrowed(self.bounding_box.get_or_init(|| {
             let rvec = v3d!(self.radius.abs(), self.radius.abs(), self.radius.abs());
 
@@ -102,3 +119,26 @@ impl Hittable for Sphere {
         }))
     }
 }
+
+/// Texture coordinates for a point on the unit sphere
+///
+/// `u` is the angle around the y axis starting at x = -1, `v` the angle from y = -1 to y = +1.
+#[inline]
+fn sphere_uv(p: &Vec3d) -> (f64, f64) {
+    let theta = (-p.y).clamp(-1.0, 1.0).acos();
+    let phi = (-p.z).atan2(p.x) + PI;
+
+    (phi / (2.0 * PI), theta / PI)
+}
+
+/// Derivatives of the point on the sphere by the texture coordinates of `sphere_uv`
+#[inline]
+fn sphere_derivatives(n: &Vec3d, radius: f64) -> (Vec3d, Vec3d) {
+    // Distance from the y axis, the sine of the angle from the pole
+    let sin_theta = (n.x * n.x + n.z * n.z).sqrt().max(1e-12);
+
+    let dpdu = 2.0 * PI * radius * v3d!(n.z, 0.0, -n.x);
+    let dpdv = PI * radius * v3d!(-n.x * n.y / sin_theta, sin_theta, -n.z * n.y / sin_theta);
+
+    (dpdu, dpdv)
+}
@@ -0,0 +1,200 @@
+use std::{borrow::Cow, sync::Arc};
+
+use crate::{
+    acceleration::Aabb,
+    core::{Color, HitRecord, Hittable, Ray, Vec3d},
+    material::MaterialConfig,
+};
+
+/// Single triangle of a mesh
+#[derive(Clone)]
+pub struct Triangle {
+    pub vertices: [Vec3d; 3],
+
+    /// Vertex normals for smooth shading, the face normal is used if missing
+    pub normals: Option<[Vec3d; 3]>,
+
+    /// Vertex texture coordinates, the barycentric coordinates are used if missing
+    pub uvs: Option<[(f64, f64); 3]>,
+
+    /// Vertex colors, multiplied with the material albedo
+    pub colors: Option<[Color; 3]>,
+
+    /// Shared by all triangles of a mesh
+    pub material: Arc<MaterialConfig>,
+}
+
+impl Triangle {
+    /// Derivatives of the point by the texture coordinates, from how they change along the edges
+    fn derivatives(&self, edge1: &Vec3d, edge2: &Vec3d) -> (Vec3d, Vec3d) {
+        let Some([uv0, uv1, uv2]) = &self.uvs else {
+            // The barycentric coordinates run along the edges
+            return (*edge1, *edge2);
+        };
+
+        let (du1, dv1) = (uv1.0 - uv0.0, uv1.1 - uv0.1);
+        let (du2, dv2) = (uv2.0 - uv0.0, uv2.1 - uv0.1);
+        let det = du1 * dv2 - dv1 * du2;
+
+        // Degenerate texture coordinates, fall back to the edges
+        if det.abs() < 1e-12 {
+            return (*edge1, *edge2);
+        }
+
+        let inv_det = 1.0 / det;
+        (
+            (dv2 * edge1 - dv1 * edge2) * inv_det,
+            (du1 * edge2 - du2 * edge1) * inv_det,
+        )
+    }
+}
+
+impl Hittable for Triangle {
+    /// Möller–Trumbore ray-triangle intersection
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        let [v0, v1, v2] = &self.vertices;
+        let edge1 = v1 - v0;
+        let edge2 = v2 - v0;
+
+        let p = ray.direction.cross(&edge2);
+        let det = edge1.dot(&p);
+
+        // No hit if the ray is parallel to the triangle
+        if det.abs() < 1e-12 {
+            return None;
+        }
+
+        let inv_det = 1.0 / det;
+        let s = ray.origin - v0;
+        let b1 = s.dot(&p) * inv_det;
+        if !(0.0..=1.0).contains(&b1) {
+            return None;
+        }
+
+        let q = s.cross(&edge1);
+        let b2 = ray.direction.dot(&q) * inv_det;
+        if b2 < 0.0 || b1 + b2 > 1.0 {
+            return None;
+        }
+
+        let t = edge2.dot(&q) * inv_det;
+        if t <= t_min || t_max <= t {
+            return None;
+        }
+
+        let b0 = 1.0 - b1 - b2;
+
+        let outward_normal = edge1.cross(&edge2).unit_vector();
+        let mut shading_normal = match &self.normals {
+            Some([n0, n1, n2]) => (b0 * n0 + b1 * n1 + b2 * n2).unit_vector(),
+            None => outward_normal,
+        };
+
+        // Vertex normals wound against the face still shade the side the ray hits
+        if shading_normal.dot(&outward_normal) < 0.0 {
+            shading_normal = -shading_normal;
+        }
+
+        let (u, v) = match &self.uvs {
+            Some([uv0, uv1, uv2]) => (
+                b0 * uv0.0 + b1 * uv1.0 + b2 * uv2.0,
+                b0 * uv0.1 + b1 * uv1.1 + b2 * uv2.1,
+            ),
+            None => (b1, b2),
+        };
+        let (dpdu, dpdv) = self.derivatives(&edge1, &edge2);
+
+        let vertex_color = self.colors.as_ref().map(|[c0, c1, c2]| b0 * c0 + b1 * c1 + b2 * c2);
+
+        let mut rec = HitRecord {
+            t,
+            normal: outward_normal,
+            shading_normal,
+            dpdu,
+            dpdv,
+            point: ray.at(t),
+            u,
+            v,
+            vertex_color,
+            front_face: false,
+            material: &self.material,
+        };
+
+        rec.set_normal_face(ray, &outward_normal);
+
+        Some(rec)
+    }
+
+    fn bounding_box(&self) -> Cow<'_, Aabb> {
+        let [v0, v1, v2] = &self.vertices;
+
+        // Triangles in an axis aligned plane are flat in one dimension
+        Cow::Owned(Aabb::from_aabb(&Aabb::from_points(v0, v1), &Aabb::from_points(v1, v2)).pad(1e-4))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{color, material::Lambertian, texture::Texture, v3d};
+    use approx::*;
+
+    fn test_triangle() -> Triangle {
+        Triangle {
+            vertices: [v3d!(0.0, 0.0, -1.0), v3d!(1.0, 0.0, -1.0), v3d!(0.0, 1.0, -1.0)],
+            normals: None,
+            uvs: None,
+            colors: None,
+            material: Arc::new(MaterialConfig::Lambertian(Lambertian {
+                albedo: Texture::Solid(color!(0.5, 0.5, 0.5)),
+            })),
+        }
+    }
+
+    #[test]
+    fn test_hit() {
+        let triangle = test_triangle();
+        let ray = Ray {
+            origin: v3d!(0.25, 0.25, 1.0),
+            direction: v3d!(0.0, 0.0, -1.0),
+            time: 0.0,
+        };
+
+        let hit = triangle.hit(&ray, 0.0, f64::INFINITY).unwrap();
+        assert_relative_eq!(hit.t, 2.0);
+        assert_relative_eq!(hit.normal.z, 1.0);
+        assert_relative_eq!(hit.u, 0.25);
+        assert_relative_eq!(hit.v, 0.25);
+    }
+
+    #[test]
+    fn test_flipped_vertex_normals() {
+        let triangle = Triangle {
+            normals: Some([v3d!(0.0, 0.0, -1.0); 3]),
+            ..test_triangle()
+        };
+
+        for (z, direction) in [(1.0, -1.0), (-3.0, 1.0)] {
+            let ray = Ray {
+                origin: v3d!(0.25, 0.25, z),
+                direction: v3d!(0.0, 0.0, direction),
+                time: 0.0,
+            };
+
+            let hit = triangle.hit(&ray, 0.0, f64::INFINITY).unwrap();
+            assert_eq!(hit.front_face, direction < 0.0);
+            assert_relative_eq!(hit.shading_normal.z, hit.normal.z);
+        }
+    }
+
+    #[test]
+    fn test_miss() {
+        let ray = Ray {
+            origin: v3d!(0.75, 0.75, 1.0),
+            direction: v3d!(0.0, 0.0, -1.0),
+            time: 0.0,
+        };
+
+        assert!(test_triangle().hit(&ray, 0.0, f64::INFINITY).is_none());
+    }
+}
@@ -1,27 +1,114 @@
 use std::borrow::Cow;
 
 use crate::{
-    acceleration::Aabb,
+    acceleration::{stats, Aabb},
     core::{HitRecord, Hittable, Ray},
-    gemeometry::Sphere,
+    gemeometry::ObjectConfig,
+    v3d_zero,
 };
 
-impl Hittable for Vec<Sphere> {
-    fn hit<'a>(&'a self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'a>> {
-        let mut hit = None;
-        let mut closest_so_far = t_max;
+/// Objects which are all tested for every ray, without an acceleration structure
+pub struct ObjectList {
+    objects: Vec<ObjectConfig>,
 
-        for obj in self {
-            if let Some(new_hit) = obj.hit(ray, t_min, closest_so_far) {
-                closest_so_far = new_hit.t;
-                hit = Some(new_hit);
-            }
+    /// Union of the boxes of all objects, computed once when the list is created
+    bounding_box: Aabb,
+}
+
+impl ObjectList {
+    pub fn new(objects: Vec<ObjectConfig>) -> Self {
+        ObjectList {
+            bounding_box: union_bounds(&objects),
+            objects,
         }
+    }
 
-        hit
+    /// Move all objects to the next frame of an animation
+    pub fn advance_frame(&mut self) {
+        self.objects.iter_mut().for_each(ObjectConfig::advance_frame);
+        self.bounding_box = union_bounds(&self.objects);
+    }
+}
+
+/// Union of the boxes of all `objects`
+///
+/// No objects give a degenerate box at the origin, which is still fine for building acceleration
+/// structures over it and is never hit.
+pub(super) fn union_bounds(objects: &[ObjectConfig]) -> Aabb {
+    let Some((first, rest)) = objects.split_first() else {
+        return Aabb::from_points(&v3d_zero!(), &v3d_zero!());
+    };
+
+    rest.iter().fold(first.bounding_box().into_owned(), |acc, obj| {
+        Aabb::from_aabb(&acc, &obj.bounding_box())
+    })
+}
+
+/// Closest hit of all `objects`, counting the work done if `COUNT` is set
+pub(super) fn hit_all<'a, const COUNT: bool>(
+    objects: &'a [ObjectConfig],
+    ray: &Ray,
+    t_min: f64,
+    t_max: f64,
+) -> Option<HitRecord<'a>> {
+    let mut hit = None;
+    let mut closest_so_far = t_max;
+
+    for obj in objects {
+        if let Some(new_hit) = stats::hit::<COUNT>(obj, ray, t_min, closest_so_far) {
+            closest_so_far = new_hit.t;
+            hit = Some(new_hit);
+        }
+    }
+
+    hit
+}
+
+impl Hittable for ObjectList {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        hit_all::<false>(&self.objects, ray, t_min, t_max)
+    }
+
+    fn hit_counted(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        hit_all::<true>(&self.objects, ray, t_min, t_max)
     }
 
     fn bounding_box(&self) -> Cow<'_, Aabb> {
-        todo!()
+        Cow::Borrowed(&self.bounding_box)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{color, gemeometry::Sphere, material::Lambertian, material::MaterialConfig, texture::Texture, v3d};
+    use approx::*;
+
+    #[test]
+    fn test_bounding_box() {
+        let material = MaterialConfig::Lambertian(Lambertian {
+            albedo: Texture::Solid(color!(0.5, 0.5, 0.5)),
+        });
+        let list = ObjectList::new(vec![
+            ObjectConfig::Sphere(Sphere::new(v3d!(-2.0, 0.0, -5.0), 1.0, material.clone())),
+            ObjectConfig::Sphere(Sphere::new(v3d!(2.0, 0.0, -5.0), 1.0, material)),
+        ]);
+
+        let bbox = list.bounding_box();
+        assert_relative_eq!(bbox.axis(0).start, -3.0);
+        assert_relative_eq!(bbox.axis(0).end, 3.0);
+    }
+
+    #[test]
+    fn test_empty_list() {
+        let list = ObjectList::new(vec![]);
+        let ray = Ray {
+            origin: v3d!(0.0, 0.0, 1.0),
+            direction: v3d!(0.0, 0.0, -1.0),
+            time: 0.0,
+        };
+
+        assert!(list.hit(&ray, 0.0, f64::INFINITY).is_none());
+        assert_eq!(list.bounding_box().surface_area(), 0.0);
     }
 }
@@ -0,0 +1,387 @@
+use std::{collections::HashMap, path::Path};
+
+use gltf::camera::Projection;
+
+use crate::{
+    acceleration::Aabb,
+    camera::CameraConfig,
+    color,
+    core::{Hittable, Mat4, TransformConfig, Vec3d},
+    gemeometry::{Instance, Mesh, ObjectConfig},
+    material::{Dielectric, DiffuseLight, Lambertian, MaterialConfig, Metal},
+    texture::Texture,
+    v3d,
+};
+
+use super::{MeshFace, MeshVertex};
+
+/// Objects and camera imported from a glTF file
+pub struct GltfScene {
+    pub objects: Vec<ObjectConfig>,
+    pub camera: CameraConfig,
+}
+
+/// Load the default scene of a `.gltf` or `.glb` file
+///
+/// Every mesh primitive becomes a `Mesh` object, shared by all nodes using the mesh and placed
+/// by an `Instance` with the transforms of the node hierarchy. The metallic-roughness materials
+/// are mapped to the closest available material, textures are ignored. The first camera in the
+/// scene is used, otherwise a camera looking at the whole scene is created. Either one focuses
+/// on the center of the scene.
+pub fn load_gltf(file_path: &str) -> Result<GltfScene, String> {
+    let error = |err: gltf::Error| format!("Unable to read glTF '{file_path}': {err}");
+
+    let gltf = gltf::Gltf::open(file_path).map_err(error)?;
+    let buffers =
+        gltf::import_buffers(&gltf.document, Path::new(file_path).parent(), gltf.blob.clone()).map_err(error)?;
+
+    let scene = gltf
+        .document
+        .default_scene()
+        .or_else(|| gltf.document.scenes().next())
+        .ok_or_else(|| format!("glTF '{file_path}' contains no scene"))?;
+
+    let mut importer = Importer {
+        file_path,
+        buffers: &buffers,
+        objects: vec![],
+        meshes: HashMap::new(),
+        camera: None,
+    };
+
+    for node in scene.nodes() {
+        importer.import_node(&node, &Mat4::identity())?;
+    }
+
+    let Importer { objects, camera, .. } = importer;
+
+    if objects.is_empty() {
+        return Err(format!("glTF '{file_path}' contains no triangles"));
+    }
+
+    let bounds = objects
+        .iter()
+        .map(|obj| obj.bounding_box().into_owned())
+        .reduce(|a, b| Aabb::from_aabb(&a, &b))
+        .unwrap();
+
+    let camera = match camera {
+        Some(camera) => focus_on(camera, &bounds),
+        None => overview_camera(&bounds),
+    };
+
+    Ok(GltfScene { objects, camera })
+}
+
+struct Importer<'a> {
+    file_path: &'a str,
+    buffers: &'a [gltf::buffer::Data],
+    objects: Vec<ObjectConfig>,
+
+    /// Meshes of the primitives of every glTF mesh in its own space, by mesh index
+    meshes: HashMap<usize, Vec<Mesh>>,
+
+    camera: Option<CameraConfig>,
+}
+
+impl<'a> Importer<'a> {
+    fn import_node(&mut self, node: &gltf::Node, parent: &Mat4) -> Result<(), String> {
+        let matrix = parent * mat4_from_columns(&node.transform().matrix());
+
+        if let Some(mesh) = node.mesh() {
+            let objects = if matrix == Mat4::identity() {
+                self.shared_meshes(&mesh)?
+                    .iter()
+                    .cloned()
+                    .map(ObjectConfig::Mesh)
+                    .collect()
+            } else if let Some(transform) = TransformConfig::from_matrix(&matrix) {
+                self.shared_meshes(&mesh)?
+                    .iter()
+                    .map(|mesh| {
+                        let object = Box::new(ObjectConfig::Mesh(mesh.clone()));
+                        Instance::new(object, transform.clone()).map(ObjectConfig::Instance)
+                    })
+                    .collect::<Result<_, _>>()?
+            } else {
+                // Sheared by the node hierarchy, which an instance can't do, so the triangles are moved
+                self.read_meshes(&mesh, &matrix)?
+                    .into_iter()
+                    .map(ObjectConfig::Mesh)
+                    .collect::<Vec<_>>()
+            };
+            self.objects.extend(objects);
+        }
+
+        if let (None, Some(camera)) = (&self.camera, node.camera()) {
+            if let Projection::Perspective(perspective) = camera.projection() {
+                // glTF cameras look along -z with +y up
+                let look_from = matrix.transform_point(&v3d!(0.0, 0.0, 0.0));
+
+                self.camera = Some(CameraConfig {
+                    look_from,
+                    look_at: look_from + matrix.transform_vector(&v3d!(0.0, 0.0, -1.0)),
+                    vup: matrix.transform_vector(&v3d!(0.0, 1.0, 0.0)),
+                    vfov: (perspective.yfov() as f64).to_degrees(),
+                    aspect_ratio: perspective.aspect_ratio().unwrap_or(16.0 / 9.0) as f64,
+                    defocus_angle: 0.0,
+                    // Set by `focus_on` once the whole scene is loaded
+                    focus_dist: 1.0,
+                });
+            }
+        }
+
+        for child in node.children() {
+            self.import_node(&child, &matrix)?;
+        }
+
+        Ok(())
+    }
+
+    /// Meshes of the primitives of `mesh` in its own space, read by the first node using it
+    fn shared_meshes(&mut self, mesh: &gltf::Mesh) -> Result<&[Mesh], String> {
+        if !self.meshes.contains_key(&mesh.index()) {
+            let meshes = self.read_meshes(mesh, &Mat4::identity())?;
+            self.meshes.insert(mesh.index(), meshes);
+        }
+
+        Ok(&self.meshes[&mesh.index()])
+    }
+
+    /// A `Mesh` for each triangle primitive of `mesh`, transformed by `matrix`
+    fn read_meshes(&self, mesh: &gltf::Mesh, matrix: &Mat4) -> Result<Vec<Mesh>, String> {
+        let mut meshes = vec![];
+
+        for primitive in mesh.primitives() {
+            if primitive.mode() != gltf::mesh::Mode::Triangles {
+                continue;
+            }
+
+            let faces = self.read_faces(&primitive, matrix)?;
+            if !faces.is_empty() {
+                let source = format!("{}#{}", self.file_path, mesh.name().unwrap_or("mesh"));
+                meshes.push(Mesh::from_faces(source, &faces, map_material(&primitive.material())));
+            }
+        }
+
+        Ok(meshes)
+    }
+
+    /// Read the triangles of a primitive, transformed by `matrix`
+    fn read_faces(&self, primitive: &gltf::Primitive, matrix: &Mat4) -> Result<Vec<MeshFace>, String> {
+        let reader = primitive.reader(|buffer| Some(&self.buffers[buffer.index()]));
+
+        let normal_matrix = matrix
+            .inverse()
+            .ok_or_else(|| format!("glTF '{}' contains a singular node transform", self.file_path))?
+            .transpose();
+
+        let positions = reader
+            .read_positions()
+            .ok_or_else(|| format!("glTF '{}' contains a primitive without positions", self.file_path))?
+            .map(|[x, y, z]| matrix.transform_point(&v3d!(x as f64, y as f64, z as f64)))
+            .collect::<Vec<_>>();
+
+        let normals = reader.read_normals().map(|normals| {
+            normals
+                .map(|[x, y, z]| {
+                    normal_matrix
+                        .transform_vector(&v3d!(x as f64, y as f64, z as f64))
+                        .unit_vector()
+                })
+                .collect::<Vec<_>>()
+        });
+
+        let uvs = reader
+            .read_tex_coords(0)
+            .map(|uvs| uvs.into_f32().map(|[u, v]| (u as f64, v as f64)).collect::<Vec<_>>());
+
+        let colors = reader.read_colors(0).map(|colors| {
+            colors
+                .into_rgb_f32()
+                .map(|[r, g, b]| color!(r as f64, g as f64, b as f64))
+                .collect::<Vec<_>>()
+        });
+
+        let indices = match reader.read_indices() {
+            Some(indices) => indices.into_u32().map(|idx| idx as usize).collect::<Vec<_>>(),
+            None => (0..positions.len()).collect(),
+        };
+
+        let vertex = |idx: usize| -> Result<MeshVertex, String> {
+            Ok(MeshVertex {
+                position: *positions
+                    .get(idx)
+                    .ok_or_else(|| format!("glTF '{}' contains an invalid index", self.file_path))?,
+                normal: normals.as_ref().and_then(|normals| normals.get(idx).copied()),
+                uv: uvs.as_ref().and_then(|uvs| uvs.get(idx).copied()),
+                color: colors.as_ref().and_then(|colors| colors.get(idx).copied()),
+            })
+        };
+
+        indices
+            .chunks_exact(3)
+            .map(|face| Ok([vertex(face[0])?, vertex(face[1])?, vertex(face[2])?]))
+            .collect()
+    }
+}
+
+/// Convert the column major glTF matrix
+fn mat4_from_columns(columns: &[[f32; 4]; 4]) -> Mat4 {
+    let mut m = [[0.0; 4]; 4];
+    for (r, row) in m.iter_mut().enumerate() {
+        for (c, value) in row.iter_mut().enumerate() {
+            *value = columns[c][r] as f64;
+        }
+    }
+    Mat4 { m }
+}
+
+/// Pick the closest material for the glTF metallic-roughness material
+fn map_material(material: &gltf::Material) -> MaterialConfig {
+    let pbr = material.pbr_metallic_roughness();
+    let [r, g, b, _] = pbr.base_color_factor();
+    let base_color = color!(r as f64, g as f64, b as f64);
+
+    let [er, eg, eb] = material.emissive_factor();
+    let emission = color!(er as f64, eg as f64, eb as f64) * material.emissive_strength().unwrap_or(1.0) as f64;
+
+    let transmission = material.transmission().map_or(0.0, |t| t.transmission_factor());
+
+    if emission.x > 0.0 || emission.y > 0.0 || emission.z > 0.0 {
+        MaterialConfig::DiffuseLight(DiffuseLight { emit: emission })
+    } else if transmission > 0.5 {
+        MaterialConfig::Dielectric(Dielectric {
+            index_of_refraction: material.ior().unwrap_or(1.5) as f64,
+            roughness: pbr.roughness_factor() as f64,
+        })
+    } else if pbr.metallic_factor() >= 0.5 {
+        MaterialConfig::Metal(Metal {
+            albedo: Texture::Solid(base_color),
+            fuzz: pbr.roughness_factor() as f64,
+        })
+    } else {
+        MaterialConfig::Lambertian(Lambertian {
+            albedo: Texture::Solid(base_color),
+        })
+    }
+}
+
+/// Center and size of a bounding box
+fn center_and_size(bbox: &Aabb) -> (Vec3d, Vec3d) {
+    let center = v3d!(
+        (bbox.axis(0).start + bbox.axis(0).end) / 2.0,
+        (bbox.axis(1).start + bbox.axis(1).end) / 2.0,
+        (bbox.axis(2).start + bbox.axis(2).end) / 2.0
+    );
+    let size = v3d!(
+        bbox.axis(0).end - bbox.axis(0).start,
+        bbox.axis(1).end - bbox.axis(1).start,
+        bbox.axis(2).end - bbox.axis(2).start
+    );
+
+    (center, size)
+}
+
+/// Focus the camera at the depth of the center of the scene, or at its size if that is behind
+fn focus_on(camera: CameraConfig, bounds: &Aabb) -> CameraConfig {
+    let (center, size) = center_and_size(bounds);
+    let depth = (center - camera.look_from).dot(&(camera.look_at - camera.look_from).unit_vector());
+
+    CameraConfig {
+        focus_dist: if depth > 0.0 { depth } else { size.length() },
+        ..camera
+    }
+}
+
+/// Camera looking at the center of all objects from the front
+fn overview_camera(bounds: &Aabb) -> CameraConfig {
+    let (center, size) = center_and_size(bounds);
+    let distance = size.length() * 1.5;
+
+    CameraConfig {
+        look_from: center + v3d!(0.0, 0.0, distance),
+        look_at: center,
+        vup: v3d!(0.0, 1.0, 0.0),
+        vfov: 40.0,
+        aspect_ratio: 16.0 / 9.0,
+        defocus_angle: 0.0,
+        focus_dist: distance,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::*;
+
+    /// Mesh placed by `object`, directly or by an instance
+    fn mesh(object: &ObjectConfig) -> &Mesh {
+        match object {
+            ObjectConfig::Mesh(mesh) => mesh,
+            ObjectConfig::Instance(instance) => mesh(&instance.object),
+            _ => panic!("not a mesh"),
+        }
+    }
+
+    #[test]
+    fn test_load_boxes() {
+        let scene = load_gltf("scenes/boxes.gltf").unwrap();
+        assert_eq!(scene.objects.len(), 3);
+
+        // Camera node is the child of a translated rig node
+        assert_relative_eq!(scene.camera.look_from.y, 1.5, epsilon = 1e-6);
+        assert_relative_eq!(scene.camera.look_from.z, 5.0, epsilon = 1e-6);
+        assert_relative_eq!(scene.camera.vfov, 40.0, epsilon = 1e-4);
+        assert!(scene.camera.look_at.z < scene.camera.look_from.z);
+        assert!((4.0..6.0).contains(&scene.camera.focus_dist));
+
+        // The gold box is translated by itself and its parent node
+        let bbox = scene.objects[1].bounding_box();
+        assert_relative_eq!((bbox.axis(0).start + bbox.axis(0).end) / 2.0, -0.8, epsilon = 1e-4);
+        assert_relative_eq!((bbox.axis(1).start + bbox.axis(1).end) / 2.0, 0.5, epsilon = 1e-4);
+
+        assert!(matches!(scene.objects[0], ObjectConfig::Mesh(_)));
+        assert!(matches!(scene.objects[1], ObjectConfig::Instance(_)));
+        assert!(matches!(mesh(&scene.objects[1]).material, MaterialConfig::Metal(_)));
+        assert!(matches!(
+            mesh(&scene.objects[2]).material,
+            MaterialConfig::Dielectric(_)
+        ));
+    }
+
+    #[test]
+    fn test_shared_mesh() {
+        let file_path = std::env::temp_dir().join(format!("weekend-ray-test-shared-{}.gltf", std::process::id()));
+        let gltf = serde_json::json!({
+            "asset": { "version": "2.0" },
+            "scene": 0,
+            "scenes": [{ "nodes": [0, 1] }],
+            "nodes": [{ "mesh": 0 }, { "mesh": 0, "translation": [2.0, 0.0, 0.0] }],
+            "meshes": [{ "primitives": [{ "attributes": { "POSITION": 0 } }] }],
+            "accessors": [{
+                "bufferView": 0,
+                "componentType": 5126,
+                "count": 3,
+                "type": "VEC3",
+                "min": [0.0, 0.0, 0.0],
+                "max": [1.0, 1.0, 0.0]
+            }],
+            "bufferViews": [{ "buffer": 0, "byteLength": 36 }],
+            "buffers": [{
+                "byteLength": 36,
+                "uri": "data:application/octet-stream;base64,AAAAAAAAAAAAAAAAAACAPwAAAAAAAAAAAAAAAAAAgD8AAAAA"
+            }]
+        });
+        std::fs::write(&file_path, gltf.to_string()).unwrap();
+
+        let scene = load_gltf(file_path.to_str().unwrap()).unwrap();
+        std::fs::remove_file(&file_path).unwrap();
+
+        assert_eq!(scene.objects.len(), 2);
+        assert!(matches!(scene.objects[0], ObjectConfig::Mesh(_)));
+        assert!(matches!(scene.objects[1], ObjectConfig::Instance(_)));
+        assert!(mesh(&scene.objects[0]).shares_geometry(mesh(&scene.objects[1])));
+        assert_relative_eq!(scene.objects[1].bounding_box().axis(0).start, 2.0, epsilon = 1e-3);
+    }
+}
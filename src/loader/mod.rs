@@ -0,0 +1,42 @@
+mod gltf_file;
+mod obj;
+mod ply;
+
+pub use gltf_file::load_gltf;
+
+use std::path::Path;
+
+use crate::core::{Color, Vec3d};
+
+/// Vertex of a loaded triangle with its optional attributes
+#[derive(Clone)]
+pub struct MeshVertex {
+    pub position: Vec3d,
+    pub normal: Option<Vec3d>,
+    pub uv: Option<(f64, f64)>,
+    pub color: Option<Color>,
+}
+
+pub type MeshFace = [MeshVertex; 3];
+
+/// Load the triangles of a mesh file, the format is selected by the file extension
+pub fn load_mesh(file_path: &str) -> Result<Vec<MeshFace>, String> {
+    let extension = Path::new(file_path)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_ascii_lowercase());
+
+    match extension.as_deref() {
+        Some("obj") => obj::parse_obj(&read_to_string(file_path)?),
+        Some("ply") => ply::parse_ply(&std::fs::read(file_path).map_err(|err| read_error(file_path, err))?),
+        _ => Err(format!("Unsupported mesh format '{file_path}'")),
+    }
+}
+
+fn read_to_string(file_path: &str) -> Result<String, String> {
+    std::fs::read_to_string(file_path).map_err(|err| read_error(file_path, err))
+}
+
+fn read_error(file_path: &str, err: std::io::Error) -> String {
+    format!("Unable to read '{file_path}': {err}")
+}
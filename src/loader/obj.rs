@@ -0,0 +1,128 @@
+use crate::core::Vec3d;
+use crate::v3d;
+
+use super::{MeshFace, MeshVertex};
+
+/// Parse the geometry of a Wavefront OBJ file
+///
+/// Only vertices (`v`), texture coordinates (`vt`), normals (`vn`) and faces (`f`) are read,
+/// polygons with more than three vertices are split into a triangle fan. Materials, groups
+/// and smoothing groups are ignored.
+pub fn parse_obj(content: &str) -> Result<Vec<MeshFace>, String> {
+    let mut positions = vec![];
+    let mut uvs = vec![];
+    let mut normals = vec![];
+    let mut faces = vec![];
+
+    for (line_idx, line) in content.lines().enumerate() {
+        let error = |msg: &str| format!("OBJ line {}: {msg}", line_idx + 1);
+
+        let mut tokens = line.split_whitespace();
+        match tokens.next() {
+            Some("v") => positions.push(parse_vec3d(tokens).ok_or_else(|| error("invalid vertex"))?),
+            Some("vn") => normals.push(parse_vec3d(tokens).ok_or_else(|| error("invalid normal"))?),
+            Some("vt") => {
+                let u = tokens.next().and_then(|u| u.parse().ok());
+                let v = tokens.next().and_then(|v| v.parse().ok()).unwrap_or(0.0);
+                uvs.push((u.ok_or_else(|| error("invalid texture coordinate"))?, v));
+            }
+            Some("f") => {
+                let polygon = tokens
+                    .map(|token| parse_face_vertex(token, &positions, &uvs, &normals))
+                    .collect::<Option<Vec<_>>>()
+                    .ok_or_else(|| error("invalid face"))?;
+
+                if polygon.len() < 3 {
+                    return Err(error("face with less than three vertices"));
+                }
+
+                for i in 1..polygon.len() - 1 {
+                    faces.push([polygon[0].clone(), polygon[i].clone(), polygon[i + 1].clone()]);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    Ok(faces)
+}
+
+fn parse_vec3d<'a>(mut tokens: impl Iterator<Item = &'a str>) -> Option<Vec3d> {
+    let mut next = || tokens.next()?.parse::<f64>().ok();
+    Some(v3d!(next()?, next()?, next()?))
+}
+
+/// Parse a face vertex like `1`, `1/2`, `1//3` or `1/2/3`
+fn parse_face_vertex(token: &str, positions: &[Vec3d], uvs: &[(f64, f64)], normals: &[Vec3d]) -> Option<MeshVertex> {
+    let mut parts = token.split('/');
+
+    let position = *positions.get(resolve_index(parts.next()?, positions.len())?)?;
+
+    let uv = match parts.next() {
+        Some(idx) if !idx.is_empty() => Some(*uvs.get(resolve_index(idx, uvs.len())?)?),
+        _ => None,
+    };
+
+    let normal = match parts.next() {
+        Some(idx) if !idx.is_empty() => Some(*normals.get(resolve_index(idx, normals.len())?)?),
+        _ => None,
+    };
+
+    Some(MeshVertex {
+        position,
+        normal,
+        uv,
+        color: None,
+    })
+}
+
+/// Convert the one based (or negative, relative to the end) OBJ index into a zero based index
+fn resolve_index(idx: &str, len: usize) -> Option<usize> {
+    let idx = idx.parse::<isize>().ok()?;
+
+    match idx {
+        1.. => Some(idx as usize - 1),
+        ..=-1 => len.checked_sub(idx.unsigned_abs()),
+        0 => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::*;
+
+    #[test]
+    fn test_parse_quad_with_attributes() {
+        let content = "# square\n\
+                       v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\n\
+                       vt 0 0\nvt 1 0\nvt 1 1\nvt 0 1\n\
+                       vn 0 0 1\n\
+                       usemtl none\n\
+                       f 1/1/1 2/2/1 3/3/1 4/4/1\n";
+
+        let faces = parse_obj(content).unwrap();
+        assert_eq!(faces.len(), 2);
+
+        let [a, b, c] = &faces[1];
+        assert_relative_eq!(a.position.x, 0.0);
+        assert_relative_eq!(b.position.y, 1.0);
+        assert_relative_eq!(c.position.x, 0.0);
+        assert_relative_eq!(c.uv.unwrap().1, 1.0);
+        assert_relative_eq!(c.normal.unwrap().z, 1.0);
+    }
+
+    #[test]
+    fn test_parse_negative_and_missing_attributes() {
+        let faces = parse_obj("v 0 0 0\nv 1 0 0\nv 0 1 0\nvn 0 0 1\nf -3//1 -2//1 -1//1\n").unwrap();
+        assert_eq!(faces.len(), 1);
+        assert!(faces[0][0].uv.is_none());
+        assert!(faces[0][0].normal.is_some());
+        assert_relative_eq!(faces[0][1].position.x, 1.0);
+    }
+
+    #[test]
+    fn test_invalid_index() {
+        assert!(parse_obj("v 0 0 0\nf 1 2 3\n").is_err());
+    }
+}
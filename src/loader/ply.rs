@@ -0,0 +1,372 @@
+use crate::{color, v3d};
+
+use super::{MeshFace, MeshVertex};
+
+/// Parse a Stanford PLY file in ASCII or binary little endian format
+///
+/// Vertices are read with their optional normals (`nx`, `ny`, `nz`), texture coordinates
+/// (`u`/`v` or `s`/`t`) and colors (`red`, `green`, `blue`). Polygons are split into a
+/// triangle fan, all other elements are skipped.
+pub fn parse_ply(content: &[u8]) -> Result<Vec<MeshFace>, String> {
+    let header_end = find_subslice(content, b"end_header").ok_or_else(|| "PLY: missing end_header".to_string())?;
+    let header = std::str::from_utf8(&content[..header_end]).map_err(|_| "PLY: invalid header".to_string())?;
+
+    // The data starts after the line break following `end_header`
+    let mut data_start = header_end + b"end_header".len();
+    while data_start < content.len() && content[data_start] != b'\n' {
+        data_start += 1;
+    }
+    let data = content.get(data_start + 1..).unwrap_or_default();
+
+    let (format, elements) = parse_header(header)?;
+
+    let mut reader = match format {
+        Format::Ascii => DataReader::Ascii(
+            std::str::from_utf8(data)
+                .map_err(|_| "PLY: invalid ascii data".to_string())?
+                .split_ascii_whitespace(),
+        ),
+        Format::BinaryLittleEndian => DataReader::Binary(data),
+    };
+
+    let mut vertices = vec![];
+    let mut faces = vec![];
+
+    for element in &elements {
+        for _ in 0..element.count {
+            let values = element
+                .properties
+                .iter()
+                .map(|property| reader.read_property(property))
+                .collect::<Result<Vec<_>, _>>()?;
+
+            match element.name.as_str() {
+                "vertex" => vertices.push(element.vertex(&values)?),
+                "face" => {
+                    let indices = element.face_indices(&values)?;
+                    if indices.len() < 3 {
+                        return Err("PLY: face with less than three vertices".to_string());
+                    }
+
+                    for i in 1..indices.len() - 1 {
+                        faces.push([indices[0], indices[i], indices[i + 1]]);
+                    }
+                }
+                _ => (),
+            }
+        }
+    }
+
+    faces
+        .into_iter()
+        .map(|face| {
+            let vertex = |idx: usize| {
+                vertices
+                    .get(idx)
+                    .cloned()
+                    .ok_or_else(|| format!("PLY: vertex index {idx} out of range"))
+            };
+            Ok([vertex(face[0])?, vertex(face[1])?, vertex(face[2])?])
+        })
+        .collect()
+}
+
+enum Format {
+    Ascii,
+    BinaryLittleEndian,
+}
+
+#[derive(Clone, Copy)]
+enum ScalarType {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64,
+}
+
+enum PropertyType {
+    Scalar(ScalarType),
+    List { count: ScalarType, item: ScalarType },
+}
+
+struct Property {
+    name: String,
+    ty: PropertyType,
+}
+
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+enum Value {
+    Scalar(f64),
+    List(Vec<f64>),
+}
+
+fn parse_header(header: &str) -> Result<(Format, Vec<Element>), String> {
+    let mut lines = header.lines();
+    if lines.next().map(str::trim) != Some("ply") {
+        return Err("PLY: missing magic number".to_string());
+    }
+
+    let mut format = None;
+    let mut elements: Vec<Element> = vec![];
+
+    for line in lines {
+        let tokens = line.split_whitespace().collect::<Vec<_>>();
+
+        match tokens.as_slice() {
+            ["format", "ascii", ..] => format = Some(Format::Ascii),
+            ["format", "binary_little_endian", ..] => format = Some(Format::BinaryLittleEndian),
+            ["format", other, ..] => return Err(format!("PLY: unsupported format '{other}'")),
+            ["element", name, count] => elements.push(Element {
+                name: name.to_string(),
+                count: count
+                    .parse()
+                    .map_err(|_| format!("PLY: invalid element count '{count}'"))?,
+                properties: vec![],
+            }),
+            ["property", "list", count, item, name] => elements
+                .last_mut()
+                .ok_or_else(|| "PLY: property before element".to_string())?
+                .properties
+                .push(Property {
+                    name: name.to_string(),
+                    ty: PropertyType::List {
+                        count: parse_scalar_type(count)?,
+                        item: parse_scalar_type(item)?,
+                    },
+                }),
+            ["property", ty, name] => elements
+                .last_mut()
+                .ok_or_else(|| "PLY: property before element".to_string())?
+                .properties
+                .push(Property {
+                    name: name.to_string(),
+                    ty: PropertyType::Scalar(parse_scalar_type(ty)?),
+                }),
+            _ => (), // comments, obj_info and empty lines
+        }
+    }
+
+    Ok((format.ok_or_else(|| "PLY: missing format".to_string())?, elements))
+}
+
+fn parse_scalar_type(name: &str) -> Result<ScalarType, String> {
+    Ok(match name {
+        "char" | "int8" => ScalarType::I8,
+        "uchar" | "uint8" => ScalarType::U8,
+        "short" | "int16" => ScalarType::I16,
+        "ushort" | "uint16" => ScalarType::U16,
+        "int" | "int32" => ScalarType::I32,
+        "uint" | "uint32" => ScalarType::U32,
+        "float" | "float32" => ScalarType::F32,
+        "double" | "float64" => ScalarType::F64,
+        _ => return Err(format!("PLY: unknown property type '{name}'")),
+    })
+}
+
+impl Element {
+    fn scalar(&self, values: &[Value], names: &[&str]) -> Option<(f64, ScalarType)> {
+        self.properties.iter().zip(values).find_map(|(property, value)| {
+            match (&property.ty, value, names.contains(&property.name.as_str())) {
+                (PropertyType::Scalar(ty), Value::Scalar(value), true) => Some((*value, *ty)),
+                _ => None,
+            }
+        })
+    }
+
+    fn vertex(&self, values: &[Value]) -> Result<MeshVertex, String> {
+        let get = |name: &str| self.scalar(values, &[name]).map(|(value, _)| value);
+        let vec3d = |x, y, z| Some(v3d!(get(x)?, get(y)?, get(z)?));
+
+        let position = vec3d("x", "y", "z").ok_or_else(|| "PLY: vertex without position".to_string())?;
+        let normal = vec3d("nx", "ny", "nz");
+
+        let uv = match (
+            self.scalar(values, &["u", "s", "texture_u", "texture_s"]),
+            self.scalar(values, &["v", "t", "texture_v", "texture_t"]),
+        ) {
+            (Some((u, _)), Some((v, _))) => Some((u, v)),
+            _ => None,
+        };
+
+        let color = match (
+            self.scalar(values, &["red", "r"]),
+            self.scalar(values, &["green", "g"]),
+            self.scalar(values, &["blue", "b"]),
+        ) {
+            (Some(r), Some(g), Some(b)) => Some(color!(to_linear(r), to_linear(g), to_linear(b))),
+            _ => None,
+        };
+
+        Ok(MeshVertex {
+            position,
+            normal,
+            uv,
+            color,
+        })
+    }
+
+    fn face_indices(&self, values: &[Value]) -> Result<Vec<usize>, String> {
+        self.properties
+            .iter()
+            .zip(values)
+            .find_map(|(property, value)| match value {
+                Value::List(list) if property.name == "vertex_indices" || property.name == "vertex_index" => Some(list),
+                _ => None,
+            })
+            .ok_or_else(|| "PLY: face without vertex indices".to_string())?
+            .iter()
+            .map(|&idx| to_index(idx))
+            .collect()
+    }
+}
+
+/// Vertex index or list length, which must be a whole number that isn't negative
+fn to_index(value: f64) -> Result<usize, String> {
+    if value < 0.0 || value.fract() != 0.0 {
+        return Err(format!("PLY: invalid index or count {value}"));
+    }
+
+    Ok(value as usize)
+}
+
+/// Integer colors are 8 bit gamma encoded values, floating point colors are taken as linear
+fn to_linear((value, ty): (f64, ScalarType)) -> f64 {
+    match ty {
+        ScalarType::F32 | ScalarType::F64 => value,
+        // Inverse of the gamma applied to the rendered image
+        _ => (value / 255.0).powi(2),
+    }
+}
+
+enum DataReader<'a> {
+    Ascii(std::str::SplitAsciiWhitespace<'a>),
+    Binary(&'a [u8]),
+}
+
+impl<'a> DataReader<'a> {
+    fn read_property(&mut self, property: &Property) -> Result<Value, String> {
+        match property.ty {
+            PropertyType::Scalar(ty) => Ok(Value::Scalar(self.read(ty)?)),
+            PropertyType::List { count, item } => {
+                let count = to_index(self.read(count)?)?;
+                Ok(Value::List(
+                    (0..count).map(|_| self.read(item)).collect::<Result<_, _>>()?,
+                ))
+            }
+        }
+    }
+
+    fn read(&mut self, ty: ScalarType) -> Result<f64, String> {
+        match self {
+            DataReader::Ascii(tokens) => tokens
+                .next()
+                .and_then(|token| token.parse::<f64>().ok())
+                .ok_or_else(|| "PLY: invalid or missing ascii value".to_string()),
+            DataReader::Binary(data) => {
+                let size = match ty {
+                    ScalarType::I8 | ScalarType::U8 => 1,
+                    ScalarType::I16 | ScalarType::U16 => 2,
+                    ScalarType::I32 | ScalarType::U32 | ScalarType::F32 => 4,
+                    ScalarType::F64 => 8,
+                };
+
+                if data.len() < size {
+                    return Err("PLY: unexpected end of binary data".to_string());
+                }
+
+                let (bytes, rest) = data.split_at(size);
+                *data = rest;
+
+                Ok(match ty {
+                    ScalarType::I8 => bytes[0] as i8 as f64,
+                    ScalarType::U8 => bytes[0] as f64,
+                    ScalarType::I16 => i16::from_le_bytes([bytes[0], bytes[1]]) as f64,
+                    ScalarType::U16 => u16::from_le_bytes([bytes[0], bytes[1]]) as f64,
+                    ScalarType::I32 => i32::from_le_bytes(bytes.try_into().unwrap()) as f64,
+                    ScalarType::U32 => u32::from_le_bytes(bytes.try_into().unwrap()) as f64,
+                    ScalarType::F32 => f32::from_le_bytes(bytes.try_into().unwrap()) as f64,
+                    ScalarType::F64 => f64::from_le_bytes(bytes.try_into().unwrap()),
+                })
+            }
+        }
+    }
+}
+
+fn find_subslice(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|window| window == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::*;
+
+    const HEADER: &str = "element vertex 4\n\
+                          property float x\nproperty float y\nproperty float z\n\
+                          property uchar red\nproperty uchar green\nproperty uchar blue\n\
+                          element face 1\n\
+                          property list uchar int vertex_indices\n\
+                          end_header\n";
+
+    #[test]
+    fn test_parse_ascii() {
+        let content = format!(
+            "ply\nformat ascii 1.0\ncomment test\n{HEADER}\
+             0 0 0 255 0 0\n1 0 0 0 255 0\n1 1 0 0 0 255\n0 1 0 255 255 255\n\
+             4 0 1 2 3\n"
+        );
+
+        let faces = parse_ply(content.as_bytes()).unwrap();
+        assert_eq!(faces.len(), 2);
+        assert_relative_eq!(faces[0][1].position.x, 1.0);
+        assert_relative_eq!(faces[0][0].color.unwrap().x, 1.0);
+        assert_relative_eq!(faces[1][2].color.unwrap().y, 1.0);
+        assert!(faces[0][0].normal.is_none());
+    }
+
+    #[test]
+    fn test_parse_binary_little_endian() {
+        let mut content = format!("ply\nformat binary_little_endian 1.0\n{HEADER}").into_bytes();
+
+        for (position, color) in [([0.0f32, 0.0, 0.0], [255u8, 0, 0]), ([1.0, 0.0, 0.0], [0, 255, 0])]
+            .into_iter()
+            .chain([([1.0, 1.0, 0.0], [0, 0, 255]), ([0.0, 1.0, 0.0], [0, 0, 0])])
+        {
+            position.iter().for_each(|p| content.extend(p.to_le_bytes()));
+            content.extend(color);
+        }
+
+        content.push(3);
+        [0i32, 2, 3].iter().for_each(|idx| content.extend(idx.to_le_bytes()));
+
+        let faces = parse_ply(&content).unwrap();
+        assert_eq!(faces.len(), 1);
+        assert_relative_eq!(faces[0][1].position.x, 1.0);
+        assert_relative_eq!(faces[0][1].position.y, 1.0);
+        assert_relative_eq!(faces[0][1].color.unwrap().z, 1.0);
+    }
+
+    #[test]
+    fn test_invalid_indices() {
+        let vertices = "0 0 0 255 0 0\n1 0 0 0 255 0\n1 1 0 0 0 255\n0 1 0 255 255 255\n";
+
+        for face in ["3 0 -1 2", "3 0 1.5 2", "-3 0 1 2", "2.5 0 1 2"] {
+            let content = format!("ply\nformat ascii 1.0\n{HEADER}{vertices}{face}\n");
+            assert!(parse_ply(content.as_bytes()).is_err(), "{face}");
+        }
+    }
+
+    #[test]
+    fn test_unsupported_format() {
+        assert!(parse_ply(b"ply\nformat binary_big_endian 1.0\nend_header\n").is_err());
+    }
+}
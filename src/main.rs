@@ -1,23 +1,29 @@
 use core::{Hittable, Ray, Vec3d};
 use std::{
+    borrow::Cow,
     fs::{self, File},
     io::BufWriter,
-    path::Path,
+    path::{Path, PathBuf},
 };
 
+use background::Background;
 use camera::{Camera, CameraConfig};
 use clap::{Parser, Subcommand};
-use gemeometry::Sphere;
+use gemeometry::{MeshCache, ObjectConfig, ObjectList, Sphere};
 use material::{Dielectric, Lambertian, Metal};
 use rand::{rngs::SmallRng, Rng, SeedableRng};
+use texture::Texture;
 
-use crate::acceleration::BvhNode;
+use crate::acceleration::{Aabb, Bvh, Grid, MAX_BVH_DEGRADATION};
 
 mod acceleration;
+mod background;
 mod camera;
 mod core;
 mod gemeometry;
+mod loader;
 mod material;
+mod texture;
 
 #[derive(Parser)]
 #[command(version)]
@@ -36,6 +42,9 @@ enum InputFormat {
 
     /// Use the file as input data
     File(RenderOptions),
+
+    /// Print statistics of the bounding volume hierarchy built for a scene file
+    BvhStats(StatsOptions),
 }
 
 #[derive(clap::Args)]
@@ -53,31 +62,79 @@ struct RenderOptions {
     #[arg(long)]
     quiet: bool,
 
-    /// Use bounding volume hierarchy as acceleration structure
-    #[arg(long)]
-    use_bvh: bool,
+    /// Acceleration structure for finding the objects hit by rays
+    #[arg(long, value_enum, default_value = "none")]
+    accel: Accel,
 
     #[arg(short, long, default_value = "100")]
     samples_per_pixel: u16,
+
+    /// Directory to save built BVHs in, later runs load them instead of building them again
+    #[arg(long)]
+    bvh_cache: Option<PathBuf>,
+
+    /// Also render the cost of tracing each primary ray as a false color heatmap, saved next to
+    /// the image as `<image>.heatmap.png`
+    #[arg(long)]
+    heatmap: bool,
+
+    /// Render an animation, moving spheres continue along their motion in every frame
+    #[arg(long, default_value = "1")]
+    frames: u16,
+}
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+enum Accel {
+    /// Test every object for every ray
+    None,
+
+    /// Bounding volume hierarchy
+    Bvh,
+
+    /// Uniform grid, for many evenly spread objects of similar size
+    Grid,
+}
+
+/// Objects of the scene in the chosen acceleration structure
+enum World {
+    List(ObjectList),
+    Bvh(Bvh<ObjectConfig>),
+    Grid(Grid<ObjectConfig>),
+}
+
+#[derive(clap::Args)]
+struct StatsOptions {
+    #[arg()]
+    file_path: String,
+
+    /// Width of the image whose primary rays are traced to measure the traversal
+    #[arg(long, default_value = "64")]
+    width: u16,
 }
 
 #[derive(serde::Serialize, serde::Deserialize)]
 struct InputData {
     camera: CameraConfig,
-    objects: Vec<Sphere>,
+
+    /// Light for rays leaving the scene, defaults to the blue sky
+    #[serde(default)]
+    background: Background,
+
+    objects: Vec<ObjectConfig>,
 }
 
 fn main() {
     let cfg = Config::parse(); // Parse arguments
 
-    let input: InputData = match cfg.command {
+    let mut input: InputData = match cfg.command {
         InputFormat::Cover { file_path } => {
             generate_random_cover_scene(&file_path);
             return;
         }
-        InputFormat::File(RenderOptions { ref file_path, .. }) => {
-            // Read input file
-            serde_json::from_str(&fs::read_to_string(file_path).expect("Unable to read input file")).unwrap()
+        InputFormat::File(RenderOptions { ref file_path, .. }) => read_input(file_path),
+        InputFormat::BvhStats(options) => {
+            print_bvh_stats(&options);
+            return;
         }
     };
 
@@ -88,31 +145,211 @@ fn main() {
 
     let start = std::time::Instant::now();
 
-    let image_width = cfg.width;
-    let camera = Camera::new(&input.camera, image_width, cfg.samples_per_pixel, cfg.depth);
+    // Load every mesh file only once, all objects using it share the same geometry
+    MeshCache::new(cfg.bvh_cache.clone()).load_all(&input.objects);
 
-    let img = if cfg.use_bvh {
-        let bvh = BvhNode::build(&input.objects.iter().collect::<Vec<_>>());
-        camera.render(&bvh, cfg.quiet)
-    } else {
-        camera.render(&input.objects, cfg.quiet)
-    };
+    let mut world = World::build(std::mem::take(&mut input.objects), cfg.accel, cfg.bvh_cache.as_deref());
 
     if !cfg.quiet {
-        println!("Rendering took {}", humantime::format_duration(start.elapsed()));
+        println!(
+            "Building the scene took {}",
+            humantime::format_duration(start.elapsed())
+        );
     }
 
+    let image_width = cfg.width;
+    let camera = Camera::new(
+        &input.camera,
+        &input.background,
+        image_width,
+        cfg.samples_per_pixel,
+        cfg.depth,
+    );
     let image_height = (image_width as f64 / camera.cfg.aspect_ratio) as usize;
 
-    // write_ppm("image.ppm", image_width as usize, image_height, &img);
-    write_png("image.png", image_width as usize, image_height, &img);
+    for frame in 0..cfg.frames {
+        if frame > 0 {
+            world = world.advance_frame(cfg.quiet);
+        }
+
+        let start = std::time::Instant::now();
+        let img = camera.render(&world, cfg.quiet);
+
+        if !cfg.quiet {
+            println!("Rendering took {}", humantime::format_duration(start.elapsed()));
+        }
+
+        let name = if cfg.frames > 1 {
+            format!("image_{frame:04}")
+        } else {
+            "image".to_string()
+        };
+
+        // write_ppm("image.ppm", image_width as usize, image_height, &img);
+        write_png(&format!("{name}.png"), image_width as usize, image_height, &img);
+
+        if cfg.heatmap {
+            let (heatmap, max) = camera.render_heatmap(&world, cfg.quiet);
+
+            if !cfg.quiet {
+                println!("Heatmap shows up to {max} box and primitive tests per ray in red");
+            }
+            write_png(
+                &format!("{name}.heatmap.png"),
+                image_width as usize,
+                image_height,
+                &heatmap,
+            );
+        }
+    }
+}
+
+impl World {
+    fn build(mut objects: Vec<ObjectConfig>, accel: Accel, bvh_cache: Option<&Path>) -> Self {
+        objects.iter_mut().for_each(ObjectConfig::build);
+
+        match accel {
+            Accel::None => World::List(ObjectList::new(objects)),
+            Accel::Bvh => World::Bvh(Bvh::build_cached(objects, bvh_cache)),
+            Accel::Grid => World::Grid(Grid::build(objects)),
+        }
+    }
+
+    /// Move all objects to the next frame of an animation and update the acceleration structure
+    fn advance_frame(self, quiet: bool) -> Self {
+        let start = std::time::Instant::now();
+
+        let (world, action) = match self {
+            World::List(mut list) => {
+                list.advance_frame();
+                return World::List(list);
+            }
+            World::Bvh(mut bvh) => {
+                bvh.primitives_mut().iter_mut().for_each(ObjectConfig::advance_frame);
+                let rebuilt = bvh.refit(Some(MAX_BVH_DEGRADATION));
+
+                let action = if rebuilt {
+                    "Rebuilding the BVH"
+                } else {
+                    "Refitting the BVH"
+                };
+                (World::Bvh(bvh), action)
+            }
+            World::Grid(grid) => {
+                let mut objects = grid.into_primitives();
+                objects.iter_mut().for_each(ObjectConfig::advance_frame);
+
+                (World::Grid(Grid::build(objects)), "Rebuilding the grid")
+            }
+        };
+
+        if !quiet {
+            println!("{action} took {}", humantime::format_duration(start.elapsed()));
+        }
+        world
+    }
+}
+
+impl Hittable for World {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<core::HitRecord<'_>> {
+        match self {
+            World::List(o) => o.hit(ray, t_min, t_max),
+            World::Bvh(o) => o.hit(ray, t_min, t_max),
+            World::Grid(o) => o.hit(ray, t_min, t_max),
+        }
+    }
+
+    fn hit_counted(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<core::HitRecord<'_>> {
+        match self {
+            World::List(o) => o.hit_counted(ray, t_min, t_max),
+            World::Bvh(o) => o.hit_counted(ray, t_min, t_max),
+            World::Grid(o) => o.hit_counted(ray, t_min, t_max),
+        }
+    }
+
+    fn bounding_box(&self) -> Cow<'_, Aabb> {
+        match self {
+            World::List(o) => o.bounding_box(),
+            World::Bvh(o) => o.bounding_box(),
+            World::Grid(o) => o.bounding_box(),
+        }
+    }
+}
+
+/// Print the shape of the scene's BVH and the work needed to trace primary rays through it
+fn print_bvh_stats(options: &StatsOptions) {
+    let mut input = read_input(&options.file_path);
+
+    let start = std::time::Instant::now();
+    MeshCache::default().load_all(&input.objects);
+    let mesh_time = start.elapsed();
+
+    let start = std::time::Instant::now();
+    input.objects.iter_mut().for_each(ObjectConfig::build);
+    let bvh = Bvh::build(input.objects);
+    let build_time = start.elapsed();
+
+    let camera = Camera::new(&input.camera, &input.background, options.width, 1, 1);
+    let mut rng = SmallRng::seed_from_u64(0);
+
+    acceleration::take_counters();
+    for h in 0..camera.image_height {
+        for w in 0..camera.image_width {
+            bvh.hit_counted(&camera.get_ray(w, h, &mut rng), 0.0001, f64::INFINITY);
+        }
+    }
+    let counters = acceleration::take_counters();
+    let rays = camera.image_width as f64 * camera.image_height as f64;
+
+    let stats = bvh.stats();
+    println!("Loading meshes:             {}", humantime::format_duration(mesh_time));
+    println!("Build time:                 {}", humantime::format_duration(build_time));
+    println!("Nodes:                      {}", stats.nodes);
+    println!("Leaves:                     {}", stats.leaves);
+    println!(
+        "Depth:                      max {}, avg {:.2}",
+        stats.max_depth, stats.avg_depth
+    );
+    println!(
+        "Primitives per leaf:        min {}, max {}, avg {:.2}",
+        stats.min_leaf_size, stats.max_leaf_size, stats.avg_leaf_size
+    );
+    println!("SAH cost:                   {:.2}", stats.sah_cost);
+    println!(
+        "Primary rays:               {} ({}x{})",
+        rays, camera.image_width, camera.image_height
+    );
+    println!("Nodes visited per ray:      {:.2}", counters.nodes as f64 / rays);
+    println!("Intersection tests per ray: {:.2}", counters.primitives as f64 / rays);
+}
+
+/// Read the scene from a json scene file or a glTF file
+fn read_input(file_path: &str) -> InputData {
+    let extension = Path::new(file_path)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_ascii_lowercase());
+
+    match extension.as_deref() {
+        Some("gltf" | "glb") => {
+            let scene = loader::load_gltf(file_path).unwrap_or_else(|err| panic!("{err}"));
+
+            InputData {
+                camera: scene.camera,
+                background: Background::default(),
+                objects: scene.objects,
+            }
+        }
+        _ => serde_json::from_str(&fs::read_to_string(file_path).expect("Unable to read input file"))
+            .unwrap_or_else(|err| panic!("Unable to read scene '{file_path}': {err}")),
+    }
 }
 
 fn generate_random_cover_scene(file_path: &str) {
     let mut world = vec![];
 
     let ground_material = Lambertian {
-        albedo: color!(0.5, 0.5, 0.5),
+        albedo: Texture::Solid(color!(0.5, 0.5, 0.5)),
     };
 
     world.push(Sphere::new(
@@ -159,7 +396,7 @@ fn generate_random_cover_scene(file_path: &str) {
                         center2,
                         0.2,
                         material::MaterialConfig::Lambertian(Lambertian {
-                            albedo: core::color::random(&mut rng) * core::color::random(&mut rng),
+                            albedo: Texture::Solid(core::color::random(&mut rng) * core::color::random(&mut rng)),
                         }),
                     ));
                 } else if choose_mat < 0.95 {
@@ -169,7 +406,9 @@ fn generate_random_cover_scene(file_path: &str) {
                         0.2,
                         material::MaterialConfig::Metal(Metal {
                             fuzz: rng.gen_range(0.0..0.5),
-                            albedo: core::color::random_range(&mut rng, 0.5..1.0) * core::color::random(&mut rng),
+                            albedo: Texture::Solid(
+                                core::color::random_range(&mut rng, 0.5..1.0) * core::color::random(&mut rng),
+                            ),
                         }),
                     ));
                 } else {
@@ -179,6 +418,7 @@ fn generate_random_cover_scene(file_path: &str) {
                         0.2,
                         material::MaterialConfig::Dielectric(Dielectric {
                             index_of_refraction: 1.5,
+                            roughness: 0.0,
                         }),
                     ));
                 }
@@ -191,6 +431,7 @@ fn generate_random_cover_scene(file_path: &str) {
         1.0,
         material::MaterialConfig::Dielectric(Dielectric {
             index_of_refraction: 1.5,
+            roughness: 0.0,
         }),
     ));
 
@@ -198,7 +439,7 @@ fn generate_random_cover_scene(file_path: &str) {
         v3d!(-4.0, 1.0, 0.0),
         1.0,
         material::MaterialConfig::Lambertian(Lambertian {
-            albedo: color!(0.4, 0.2, 0.1),
+            albedo: Texture::Solid(color!(0.4, 0.2, 0.1)),
         }),
     ));
 
@@ -206,7 +447,7 @@ fn generate_random_cover_scene(file_path: &str) {
         v3d!(-4.0, 1.0, 0.0),
         1.0,
         material::MaterialConfig::Lambertian(Lambertian {
-            albedo: color!(0.4, 0.2, 0.1),
+            albedo: Texture::Solid(color!(0.4, 0.2, 0.1)),
         }),
     ));
 
@@ -215,7 +456,7 @@ fn generate_random_cover_scene(file_path: &str) {
         1.0,
         material::MaterialConfig::Metal(Metal {
             fuzz: rng.gen_range(0.0..0.5),
-            albedo: color!(0.7, 0.6, 0.5),
+            albedo: Texture::Solid(color!(0.7, 0.6, 0.5)),
         }),
     ));
 
@@ -232,7 +473,8 @@ fn generate_random_cover_scene(file_path: &str) {
 
     let data = InputData {
         camera: cam,
-        objects: world,
+        background: Background::default(),
+        objects: world.into_iter().map(ObjectConfig::Sphere).collect(),
     };
 
     let path = Path::new(file_path);
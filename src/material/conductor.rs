@@ -0,0 +1,155 @@
+use std::ops::Range;
+
+use crate::{
+    color,
+    core::{Color, HitRecord, Ray, Vec3d},
+};
+
+use super::{
+    microfacet::{self, Ggx, ShadingFrame},
+    Material, ScatterEval, ScatterResult,
+};
+
+/// Rough metal with GGX microfacets and the Fresnel reflectance of its complex index of refraction
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+pub struct Conductor {
+    pub ior: ConductorIor,
+
+    /// Perceptual roughness in [0, 1], along the tangent (the `u` texture direction) if
+    /// `roughness_v` is given
+    pub roughness: f64,
+
+    /// Roughness along the bitangent (the `v` texture direction) for anisotropic metals
+    #[serde(default)]
+    pub roughness_v: Option<f64>,
+}
+
+/// Complex index of refraction `eta + i k` for red, green and blue
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+pub enum ConductorIor {
+    Gold,
+    Copper,
+    Silver,
+    Aluminum,
+
+    #[serde(untagged)]
+    Custom {
+        eta: Color,
+        k: Color,
+    },
+}
+
+impl ConductorIor {
+    /// `eta` and `k` at about 650, 550 and 450 nm
+    fn eta_k(&self) -> (Color, Color) {
+        match self {
+            ConductorIor::Gold => (color!(0.143, 0.374, 1.442), color!(3.983, 2.385, 1.603)),
+            ConductorIor::Copper => (color!(0.200, 0.924, 1.102), color!(3.912, 2.452, 2.142)),
+            ConductorIor::Silver => (color!(0.155, 0.117, 0.138), color!(4.828, 3.122, 2.147)),
+            ConductorIor::Aluminum => (color!(1.657, 0.880, 0.521), color!(9.224, 6.270, 4.837)),
+            ConductorIor::Custom { eta, k } => (*eta, *k),
+        }
+    }
+
+    fn fresnel(&self, cos_theta: f64) -> Color {
+        let (eta, k) = self.eta_k();
+
+        color!(
+            fresnel_conductor(cos_theta, eta.x, k.x),
+            fresnel_conductor(cos_theta, eta.y, k.y),
+            fresnel_conductor(cos_theta, eta.z, k.z)
+        )
+    }
+}
+
+impl Conductor {
+    fn distribution(&self) -> Ggx {
+        Ggx::new(self.roughness, self.roughness_v.unwrap_or(self.roughness))
+    }
+}
+
+impl Material for Conductor {
+    fn scatter(&self, rnd: &mut dyn FnMut(Range<f64>) -> f64, ray: &Ray, hit: &HitRecord) -> Option<ScatterResult> {
+        let frame = ShadingFrame::new(hit);
+        let wo = frame.to_local(&-ray.direction.unit_vector());
+        if wo.z <= 0.0 {
+            return None;
+        }
+
+        let ggx = self.distribution();
+        let h = ggx.sample_visible_normal(&wo, rnd(0.0..1.0), rnd(0.0..1.0));
+        let wi = microfacet::reflect(&wo, &h);
+        if wi.z <= 0.0 {
+            return None;
+        }
+
+        // BSDF * cosine / pdf, most factors cancel with sampling the visible normals
+        Some(ScatterResult {
+            attenuation: self.ior.fresnel(wo.dot(&h)) * (ggx.g2(&wo, &wi) / ggx.g1(&wo)),
+            ray: hit.spawn_ray(frame.to_world(&wi), ray.time),
+        })
+    }
+
+    fn eval(&self, ray: &Ray, hit: &HitRecord, direction: &Vec3d) -> Option<ScatterEval> {
+        let frame = ShadingFrame::new(hit);
+        let wo = frame.to_local(&-ray.direction.unit_vector());
+        let wi = frame.to_local(&direction.unit_vector());
+        if wo.z <= 0.0 || wi.z <= 0.0 {
+            return Some(ScatterEval {
+                value: color!(0.0, 0.0, 0.0),
+                pdf: 0.0,
+            });
+        }
+
+        let ggx = self.distribution();
+        let h = (wo + wi).unit_vector();
+
+        Some(ScatterEval {
+            value: self.ior.fresnel(wo.dot(&h)) * (ggx.d(&h) * ggx.g2(&wo, &wi) / (4.0 * wo.z)),
+            pdf: ggx.reflection_pdf(&wo, &wi),
+        })
+    }
+}
+
+/// Fresnel reflectance of unpolarized light from air onto a conductor with `eta + i k`
+#[inline]
+fn fresnel_conductor(cos_theta: f64, eta: f64, k: f64) -> f64 {
+    let cos2 = cos_theta.clamp(0.0, 1.0).powi(2);
+    let sin2 = 1.0 - cos2;
+    let (eta2, k2) = (eta * eta, k * k);
+
+    let t0 = eta2 - k2 - sin2;
+    let a2_plus_b2 = (t0 * t0 + 4.0 * eta2 * k2).sqrt();
+    let t1 = a2_plus_b2 + cos2;
+    let a = (0.5 * (a2_plus_b2 + t0)).max(0.0).sqrt();
+    let t2 = 2.0 * cos_theta.clamp(0.0, 1.0) * a;
+    let rs = (t1 - t2) / (t1 + t2);
+
+    let t3 = cos2 * a2_plus_b2 + sin2 * sin2;
+    let t4 = t2 * sin2;
+    let rp = rs * (t3 - t4) / (t3 + t4);
+
+    0.5 * (rp + rs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::*;
+
+    #[test]
+    fn test_fresnel_conductor() {
+        let (eta, k) = (0.2, 3.9);
+
+        // Normal incidence has a closed form
+        let expected = ((eta - 1.0f64).powi(2) + k * k) / ((eta + 1.0f64).powi(2) + k * k);
+        assert_relative_eq!(fresnel_conductor(1.0, eta, k), expected, epsilon = 1e-12);
+
+        // Grazing light is reflected completely
+        assert_relative_eq!(fresnel_conductor(0.0, eta, k), 1.0, epsilon = 1e-12);
+
+        // Gold reflects red more than blue
+        let gold = ConductorIor::Gold.fresnel(1.0);
+        assert!(gold.x > 0.9 && gold.z < 0.4);
+    }
+}
@@ -1,11 +1,19 @@
 use crate::color;
 use crate::core::{HitRecord, Ray, Vec3d};
 
-use super::{Material, ScatterResult};
+use super::{
+    microfacet::{self, Ggx, ShadingFrame},
+    Material, ScatterResult,
+};
 
+/// Glass, water and other transparent materials, smooth or frosted
 #[derive(Clone, serde::Serialize, serde::Deserialize)]
 pub struct Dielectric {
     pub index_of_refraction: f64,
+
+    /// Perceptual roughness in [0, 1] of GGX microfacets, 0 is perfectly smooth
+    #[serde(default)]
+    pub roughness: f64,
 }
 
 impl Material for Dielectric {
@@ -15,36 +23,81 @@ impl Material for Dielectric {
         ray: &Ray,
         hit: &HitRecord,
     ) -> Option<ScatterResult> {
-        let refraction_ratio = if hit.front_face {
-            1.0 / self.index_of_refraction
-        } else {
+        // Index of the side the ray goes to over the side it comes from
+        let eta = if hit.front_face {
             self.index_of_refraction
+        } else {
+            1.0 / self.index_of_refraction
         };
 
-        let unit_direction = ray.direction.unit_vector();
-
-        let cos_theta = (-unit_direction).dot(&hit.normal).min(1.0);
-        let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
+        let new_direction = if self.roughness > 0.0 {
+            self.scatter_rough(rnd, ray, hit, eta)?
+        } else {
+            let unit_direction = ray.direction.unit_vector();
 
-        let cannot_refract = refraction_ratio * sin_theta > 1.0;
+            let cos_theta = (-unit_direction).dot(&hit.shading_normal).min(1.0);
 
-        let new_direction = if cannot_refract || reflectance(cos_theta, refraction_ratio) > rnd(0.0..1.0) {
-            reflect(&unit_direction, &hit.normal)
-        } else {
-            refract(&unit_direction, &hit.normal, refraction_ratio, cos_theta)
+            if fresnel_dielectric(cos_theta, eta) > rnd(0.0..1.0) {
+                reflect(&unit_direction, &hit.shading_normal)
+            } else {
+                refract(&unit_direction, &hit.shading_normal, 1.0 / eta, cos_theta)
+            }
         };
 
         Some(ScatterResult {
             attenuation: color!(1.0, 1.0, 1.0),
-            ray: Ray {
-                origin: hit.point,
-                direction: new_direction,
-                time: ray.time,
-            },
+            ray: hit.spawn_ray(new_direction, ray.time),
         })
     }
 }
 
+impl Dielectric {
+    /// Reflect or refract on a microfacet sampled from the visible GGX normals
+    ///
+    /// Reflection and refraction are chosen by the Fresnel reflectance, which cancels it in the
+    /// weight. The remaining shadowing weight `g2 / g1` is close to 1 for moderate roughness,
+    /// directions are kept with that probability to keep the attenuation white. Like the smooth
+    /// glass, radiance isn't scaled by `eta²` on refraction.
+    fn scatter_rough(
+        &self,
+        rnd: &mut dyn FnMut(std::ops::Range<f64>) -> f64,
+        ray: &Ray,
+        hit: &HitRecord,
+        eta: f64,
+    ) -> Option<Vec3d> {
+        let frame = ShadingFrame::new(hit);
+        let wo = frame.to_local(&-ray.direction.unit_vector());
+        if wo.z <= 0.0 {
+            return None;
+        }
+
+        let ggx = Ggx::new(self.roughness, self.roughness);
+        let h = ggx.sample_visible_normal(&wo, rnd(0.0..1.0), rnd(0.0..1.0));
+        let cos_i = wo.dot(&h);
+
+        let wi = if fresnel_dielectric(cos_i, eta) > rnd(0.0..1.0) {
+            let wi = microfacet::reflect(&wo, &h);
+            if wi.z <= 0.0 {
+                return None;
+            }
+            wi
+        } else {
+            let cos_t = (1.0 - (1.0 - cos_i * cos_i) / (eta * eta)).max(0.0).sqrt();
+            let wi = -wo / eta + (cos_i / eta - cos_t) * h;
+            if wi.z >= 0.0 {
+                return None;
+            }
+            wi
+        };
+
+        if ggx.g2(&wo, &wi) / ggx.g1(&wo) < rnd(0.0..1.0) {
+            return None;
+        }
+
+        Some(frame.to_world(&wi))
+    }
+}
+
 #[inline(always)]
 fn reflect(ray_in: &Vec3d, normal: &Vec3d) -> Vec3d {
     ray_in - 2.0 * ray_in.dot(normal) * normal
@@ -57,13 +110,68 @@ fn refract(uv: &Vec3d, normal: &Vec3d, etai_over_etat: f64, cos_theta: f64) -> V
     ray_out_perp + ray_out_parallel
 }
 
-/// Calculate the reflectance
+/// Fresnel reflectance of unpolarized light, `eta` is the index of the far side over the near side
 ///
-/// Use Schlick's approximation for reflectance.
-#[inline(always)]
-fn reflectance(cosine: f64, refraction_ratio: f64) -> f64 {
-    let r0 = (1.0 - refraction_ratio) / (1.0 + refraction_ratio);
-    let r0 = r0 * r0;
+/// Returns 1 for total internal reflection.
+#[inline]
+fn fresnel_dielectric(cos_i: f64, eta: f64) -> f64 {
+    let cos_i = cos_i.clamp(0.0, 1.0);
+    let sin2_t = (1.0 - cos_i * cos_i) / (eta * eta);
+    if sin2_t >= 1.0 {
+        return 1.0;
+    }
+
+    let cos_t = (1.0 - sin2_t).sqrt();
+    let rs = (cos_i - eta * cos_t) / (cos_i + eta * cos_t);
+    let rp = (eta * cos_i - cos_t) / (eta * cos_i + cos_t);
+
+    0.5 * (rs * rs + rp * rp)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{material::MaterialConfig, v3d};
+    use approx::*;
+    use rand::{rngs::SmallRng, Rng, SeedableRng};
 
-    r0 + (1.0 - r0) * (1.0 - cosine).powf(5.0)
+    #[test]
+    fn test_fresnel_dielectric() {
+        assert_relative_eq!(fresnel_dielectric(1.0, 1.5), 0.04, epsilon = 1e-12);
+        assert_relative_eq!(fresnel_dielectric(0.0, 1.5), 1.0, epsilon = 1e-12);
+
+        // Total internal reflection leaving glass at more than about 42 degrees
+        assert_eq!(fresnel_dielectric(0.7, 1.0 / 1.5), 1.0);
+        assert!(fresnel_dielectric(0.8, 1.0 / 1.5) < 1.0);
+    }
+
+    #[test]
+    fn test_rough_scatter() {
+        let glass = Dielectric {
+            index_of_refraction: 1.5,
+            roughness: 0.3,
+        };
+        let material = MaterialConfig::Dielectric(glass.clone());
+        let hit = HitRecord::on_xy_plane(&material, 0.0, 0.0);
+        let ray = Ray {
+            origin: v3d!(0.0, 0.0, 1.0),
+            direction: v3d!(0.3, 0.0, -1.0),
+            time: 0.0,
+        };
+
+        let mut rng = SmallRng::seed_from_u64(3);
+        let mut rnd = |range| rng.gen_range(range);
+        let (mut reflected, mut refracted) = (0, 0);
+        for _ in 0..10_000 {
+            match glass.scatter(&mut rnd, &ray, &hit) {
+                Some(scatter) if scatter.ray.direction.z > 0.0 => reflected += 1,
+                Some(_) => refracted += 1,
+                None => {}
+            }
+        }
+
+        // Mostly refracted, a few percent reflected and little energy lost to shadowing
+        assert!((200..1000).contains(&reflected));
+        assert!(reflected + refracted > 9000);
+    }
 }
@@ -0,0 +1,22 @@
+use std::ops::Range;
+
+use crate::core::{Color, HitRecord, Ray};
+
+use super::{Material, ScatterResult};
+
+/// Emissive material, turns the surface into an area light
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+pub struct DiffuseLight {
+    /// Emitted radiance, components may be larger than 1.0
+    pub emit: Color,
+}
+
+impl Material for DiffuseLight {
+    fn scatter(&self, _rnd: &mut dyn FnMut(Range<f64>) -> f64, _ray: &Ray, _hit: &HitRecord) -> Option<ScatterResult> {
+        None
+    }
+
+    fn emitted(&self, _hit: &HitRecord) -> Color {
+        self.emit
+    }
+}
@@ -1,33 +1,53 @@
-use std::ops::Range;
+use std::{f64::consts::FRAC_1_PI, ops::Range};
 
 use crate::{
     core::Color,
     core::{HitRecord, Ray, Vec3d},
+    texture::Texture,
 };
 
-use super::{Material, ScatterResult};
+use super::{Material, ScatterEval, ScatterResult};
 
 #[derive(Clone, serde::Serialize, serde::Deserialize)]
 pub struct Lambertian {
-    pub albedo: Color,
+    pub albedo: Texture,
+}
+
+impl Lambertian {
+    /// Albedo at the hit point, tinted by the vertex color of meshes
+    #[inline]
+    fn albedo_at(&self, hit: &HitRecord) -> Color {
+        let albedo = self.albedo.value(hit.u, hit.v, &hit.point);
+
+        match hit.vertex_color {
+            Some(vertex_color) => albedo * vertex_color,
+            None => albedo,
+        }
+    }
 }
 
 impl Material for Lambertian {
     fn scatter(&self, mut rnd: &mut dyn FnMut(Range<f64>) -> f64, ray: &Ray, hit: &HitRecord) -> Option<ScatterResult> {
-        let mut scatter_direction = hit.normal + Vec3d::random_unit_vector_rng_fn(&mut rnd);
+        let mut scatter_direction = hit.shading_normal + Vec3d::random_unit_vector_rng_fn(&mut rnd);
 
         // Catch degenerate scatter direction
         if scatter_direction.is_near_zero() {
-            scatter_direction = hit.normal;
+            scatter_direction = hit.shading_normal;
         }
 
         Some(ScatterResult {
-            attenuation: self.albedo,
-            ray: Ray {
-                origin: hit.point,
-                direction: scatter_direction,
-                time: ray.time,
-            },
+            attenuation: self.albedo_at(hit),
+            ray: hit.spawn_ray(scatter_direction, ray.time),
+        })
+    }
+
+    fn eval(&self, _ray: &Ray, hit: &HitRecord, direction: &Vec3d) -> Option<ScatterEval> {
+        // The scatter direction is cosine distributed around the normal
+        let cosine = hit.shading_normal.dot(&direction.unit_vector()).max(0.0);
+
+        Some(ScatterEval {
+            value: self.albedo_at(hit) * cosine * FRAC_1_PI,
+            pdf: cosine * FRAC_1_PI,
         })
     }
 }
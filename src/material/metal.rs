@@ -1,29 +1,29 @@
 use std::ops::Range;
 
 use crate::core::{
-    Color, Vec3d, {HitRecord, Ray},
+    Vec3d, {HitRecord, Ray},
 };
+use crate::texture::Texture;
 
 use super::{Material, ScatterResult};
 
 #[derive(Clone, serde::Serialize, serde::Deserialize)]
 pub struct Metal {
-    pub albedo: Color,
+    pub albedo: Texture,
     pub fuzz: f64,
 }
 
 impl Material for Metal {
     fn scatter(&self, mut rnd: &mut dyn FnMut(Range<f64>) -> f64, ray: &Ray, hit: &HitRecord) -> Option<ScatterResult> {
-        let reflected = reflect(&ray.direction.unit_vector(), &hit.normal);
-        let scattered = Ray {
-            origin: hit.point,
-            direction: reflected + self.fuzz * Vec3d::random_unit_vector_rng_fn(&mut rnd),
-            time: ray.time,
-        };
+        let reflected = reflect(&ray.direction.unit_vector(), &hit.shading_normal);
+        let scattered = hit.spawn_ray(
+            reflected + self.fuzz * Vec3d::random_unit_vector_rng_fn(&mut rnd),
+            ray.time,
+        );
 
-        if scattered.direction.dot(&hit.normal) > 0.0 {
+        if scattered.direction.dot(&hit.shading_normal) > 0.0 {
             Some(ScatterResult {
-                attenuation: self.albedo,
+                attenuation: self.albedo.value(hit.u, hit.v, &hit.point),
                 ray: scattered,
             })
         } else {
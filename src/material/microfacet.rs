@@ -0,0 +1,185 @@
+use std::f64::consts::PI;
+
+use crate::{
+    core::{HitRecord, Vec3d},
+    v3d,
+};
+
+/// Smallest GGX alpha, smoother surfaces make the distribution numerically unstable
+const MIN_ALPHA: f64 = 1e-3;
+
+/// Orthonormal frame around the shading normal, with the tangent along the `u` texture direction
+pub struct ShadingFrame {
+    tangent: Vec3d,
+    bitangent: Vec3d,
+    normal: Vec3d,
+}
+
+impl ShadingFrame {
+    pub fn new(hit: &HitRecord) -> Self {
+        let normal = hit.shading_normal;
+        let mut tangent = hit.dpdu - hit.dpdu.dot(&normal) * normal;
+
+        // Surfaces without texture coordinates get any tangent
+        if tangent.is_near_zero() {
+            let axis = if normal.x.abs() > 0.9 {
+                v3d!(0.0, 1.0, 0.0)
+            } else {
+                v3d!(1.0, 0.0, 0.0)
+            };
+            tangent = axis - axis.dot(&normal) * normal;
+        }
+        let tangent = tangent.unit_vector();
+
+        ShadingFrame {
+            bitangent: normal.cross(&tangent),
+            tangent,
+            normal,
+        }
+    }
+
+    #[inline]
+    pub fn to_local(&self, v: &Vec3d) -> Vec3d {
+        v3d!(v.dot(&self.tangent), v.dot(&self.bitangent), v.dot(&self.normal))
+    }
+
+    #[inline]
+    pub fn to_world(&self, v: &Vec3d) -> Vec3d {
+        v.x * self.tangent + v.y * self.bitangent + v.z * self.normal
+    }
+}
+
+/// Trowbridge-Reitz (GGX) distribution of microfacet normals
+///
+/// All directions are in the local shading frame with the normal along z, the roughness along
+/// the tangent is `alpha_x` and along the bitangent `alpha_y`.
+pub struct Ggx {
+    alpha_x: f64,
+    alpha_y: f64,
+}
+
+impl Ggx {
+    /// Distribution for perceptual roughness values in [0, 1], alpha is their square
+    pub fn new(roughness_x: f64, roughness_y: f64) -> Self {
+        Ggx {
+            alpha_x: (roughness_x * roughness_x).max(MIN_ALPHA),
+            alpha_y: (roughness_y * roughness_y).max(MIN_ALPHA),
+        }
+    }
+
+    /// Density of microfacets with the normal `h`
+    pub fn d(&self, h: &Vec3d) -> f64 {
+        if h.z <= 0.0 {
+            return 0.0;
+        }
+
+        let e = (h.x / self.alpha_x).powi(2) + (h.y / self.alpha_y).powi(2) + h.z * h.z;
+        1.0 / (PI * self.alpha_x * self.alpha_y * e * e)
+    }
+
+    /// Smith's auxiliary function, the shadowed microfacet area per visible area
+    fn lambda(&self, w: &Vec3d) -> f64 {
+        let tan2 = ((self.alpha_x * w.x).powi(2) + (self.alpha_y * w.y).powi(2)) / (w.z * w.z);
+        0.5 * (-1.0 + (1.0 + tan2).sqrt())
+    }
+
+    /// Fraction of microfacets visible from `w`
+    pub fn g1(&self, w: &Vec3d) -> f64 {
+        1.0 / (1.0 + self.lambda(w))
+    }
+
+    /// Fraction of microfacets visible from both directions, height correlated
+    pub fn g2(&self, wo: &Vec3d, wi: &Vec3d) -> f64 {
+        1.0 / (1.0 + self.lambda(wo) + self.lambda(wi))
+    }
+
+    /// Microfacet normal sampled from the normals visible from `wo`, which must be above the surface
+    ///
+    /// Eric Heitz, "Sampling the GGX Distribution of Visible Normals", JCGT 2018. The density is
+    /// `g1(wo) * max(0, wo·h) * d(h) / wo.z`.
+    pub fn sample_visible_normal(&self, wo: &Vec3d, u1: f64, u2: f64) -> Vec3d {
+        // Stretch to the configuration with alpha 1, where visible normals are a projected hemisphere
+        let vh = v3d!(self.alpha_x * wo.x, self.alpha_y * wo.y, wo.z).unit_vector();
+
+        let len_sq = vh.x * vh.x + vh.y * vh.y;
+        let t1 = if len_sq > 0.0 {
+            v3d!(-vh.y, vh.x, 0.0) / len_sq.sqrt()
+        } else {
+            v3d!(1.0, 0.0, 0.0)
+        };
+        let t2 = vh.cross(&t1);
+
+        // Uniform point on a disk, squashed to the part of the hemisphere visible from `vh`
+        let r = u1.sqrt();
+        let phi = 2.0 * PI * u2;
+        let p1 = r * phi.cos();
+        let s = 0.5 * (1.0 + vh.z);
+        let p2 = (1.0 - s) * (1.0 - p1 * p1).sqrt() + s * r * phi.sin();
+
+        let nh = p1 * t1 + p2 * t2 + (1.0 - p1 * p1 - p2 * p2).max(0.0).sqrt() * vh;
+
+        v3d!(self.alpha_x * nh.x, self.alpha_y * nh.y, nh.z.max(1e-6)).unit_vector()
+    }
+
+    /// Density of sampling the reflected direction `wi` through `sample_visible_normal`
+    pub fn reflection_pdf(&self, wo: &Vec3d, wi: &Vec3d) -> f64 {
+        let h = (wo + wi).unit_vector();
+        self.g1(wo) * self.d(&h) / (4.0 * wo.z)
+    }
+}
+
+#[inline(always)]
+pub fn reflect(v: &Vec3d, n: &Vec3d) -> Vec3d {
+    2.0 * v.dot(n) * n - v
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::SmallRng, Rng, SeedableRng};
+
+    #[test]
+    fn test_distribution_normalized() {
+        // The projected area of all microfacets is the area of the surface
+        let ggx = Ggx::new(0.5, 0.8);
+        let mut rng = SmallRng::seed_from_u64(1);
+        let n = 400_000;
+
+        let sum: f64 = (0..n)
+            .map(|_| {
+                // Uniform directions on the hemisphere, density 1 / 2π
+                let z: f64 = rng.gen();
+                let phi = 2.0 * PI * rng.gen::<f64>();
+                let r = (1.0 - z * z).sqrt();
+                let h = v3d!(r * phi.cos(), r * phi.sin(), z);
+                ggx.d(&h) * h.z * 2.0 * PI
+            })
+            .sum();
+
+        assert!((sum / n as f64 - 1.0).abs() < 0.02);
+    }
+
+    #[test]
+    fn test_sampling_matches_pdf() {
+        // Integrating 1 over the reflected directions in the upper hemisphere gives its solid
+        // angle, if `reflection_pdf` is the density of the sampled directions
+        let ggx = Ggx::new(0.6, 0.6);
+        let wo = v3d!(0.5, 0.1, 0.8).unit_vector();
+        let mut rng = SmallRng::seed_from_u64(2);
+        let n = 400_000;
+
+        let sum: f64 = (0..n)
+            .map(|_| {
+                let h = ggx.sample_visible_normal(&wo, rng.gen(), rng.gen());
+                let wi = reflect(&wo, &h);
+                if wi.z > 0.0 {
+                    1.0 / ggx.reflection_pdf(&wo, &wi)
+                } else {
+                    0.0
+                }
+            })
+            .sum();
+
+        assert!((sum / n as f64 / (2.0 * PI) - 1.0).abs() < 0.05);
+    }
+}
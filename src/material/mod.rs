@@ -1,17 +1,38 @@
+mod conductor;
 mod dielectric;
+mod diffuse_light;
 mod lamertian;
 mod metal;
+mod microfacet;
+mod normal_map;
 
+pub use conductor::Conductor;
 pub use dielectric::Dielectric;
+pub use diffuse_light::DiffuseLight;
 pub use lamertian::Lambertian;
 pub use metal::Metal;
+pub use normal_map::NormalMapped;
 
 use std::ops::Range;
 
-use crate::core::{Color, HitRecord, Ray};
+use crate::color;
+use crate::core::{Color, HitRecord, Ray, Vec3d};
 
 pub trait Material: Send + Sync {
     fn scatter(&self, rnd: &mut dyn FnMut(Range<f64>) -> f64, ray: &Ray, hit: &HitRecord) -> Option<ScatterResult>;
+
+    /// Light emitted by the material at the hit point, black for non emissive materials
+    fn emitted(&self, _hit: &HitRecord) -> Color {
+        color!(0.0, 0.0, 0.0)
+    }
+
+    /// Evaluate the scattering of `ray` towards `direction`, used to combine light and material sampling
+    ///
+    /// Returns `None` for materials with a delta distribution (mirrors, glass), which can't
+    /// be hit by a light sample.
+    fn eval(&self, _ray: &Ray, _hit: &HitRecord, _direction: &Vec3d) -> Option<ScatterEval> {
+        None
+    }
 }
 
 pub struct ScatterResult {
@@ -19,12 +40,23 @@ pub struct ScatterResult {
     pub attenuation: Color,
 }
 
+pub struct ScatterEval {
+    /// BSDF multiplied by the cosine to the surface normal
+    pub value: Color,
+
+    /// Probability density of `scatter` choosing the direction, with respect to solid angle
+    pub pdf: f64,
+}
+
 #[non_exhaustive]
 #[derive(Clone, serde::Serialize, serde::Deserialize)]
 pub enum MaterialConfig {
     Lambertian(Lambertian),
     Metal(Metal),
+    Conductor(Conductor),
     Dielectric(Dielectric),
+    DiffuseLight(DiffuseLight),
+    NormalMapped(NormalMapped),
 }
 
 impl Material for MaterialConfig {
@@ -33,7 +65,29 @@ impl Material for MaterialConfig {
         match &self {
             MaterialConfig::Lambertian(m) => m.scatter(rnd, ray, hit),
             MaterialConfig::Metal(m) => m.scatter(rnd, ray, hit),
+            MaterialConfig::Conductor(m) => m.scatter(rnd, ray, hit),
             MaterialConfig::Dielectric(m) => m.scatter(rnd, ray, hit),
+            MaterialConfig::DiffuseLight(m) => m.scatter(rnd, ray, hit),
+            MaterialConfig::NormalMapped(m) => m.scatter(rnd, ray, hit),
+        }
+    }
+
+    #[inline]
+    fn emitted(&self, hit: &HitRecord) -> Color {
+        match &self {
+            MaterialConfig::DiffuseLight(m) => m.emitted(hit),
+            MaterialConfig::NormalMapped(m) => m.emitted(hit),
+            _ => color!(0.0, 0.0, 0.0),
+        }
+    }
+
+    #[inline]
+    fn eval(&self, ray: &Ray, hit: &HitRecord, direction: &Vec3d) -> Option<ScatterEval> {
+        match &self {
+            MaterialConfig::Lambertian(m) => m.eval(ray, hit, direction),
+            MaterialConfig::Conductor(m) => m.eval(ray, hit, direction),
+            MaterialConfig::NormalMapped(m) => m.eval(ray, hit, direction),
+            _ => None,
         }
     }
 }
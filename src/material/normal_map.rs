@@ -0,0 +1,178 @@
+use std::ops::Range;
+
+use crate::{
+    core::{color, Color, HitRecord, Ray, Vec3d},
+    texture::{ImageTexture, Texture},
+};
+
+use super::{Material, MaterialConfig, ScatterEval, ScatterResult};
+
+/// Step in texture coordinates for the finite differences of bump maps
+const BUMP_DELTA: f64 = 1e-3;
+
+/// Any material with its shading normal perturbed by a normal or bump map
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+pub struct NormalMapped {
+    pub material: Box<MaterialConfig>,
+    pub map: NormalMap,
+}
+
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+pub enum NormalMap {
+    /// RGB image of normals in the tangent frame of the texture coordinates, blue is unperturbed
+    Normal {
+        image: ImageTexture,
+
+        /// Scales the tilt of the normals, 0 disables the map
+        #[serde(default = "default_strength")]
+        strength: f64,
+    },
+
+    /// Height field from the luminance of any texture, multiplied by `scale`
+    Bump { texture: Texture, scale: f64 },
+}
+
+fn default_strength() -> f64 {
+    1.0
+}
+
+impl NormalMapped {
+    /// The hit as the wrapped material sees it
+    #[inline]
+    fn mapped<'a>(&self, hit: &HitRecord<'a>) -> HitRecord<'a> {
+        HitRecord {
+            shading_normal: self.shading_normal(hit),
+            ..*hit
+        }
+    }
+
+    fn shading_normal(&self, hit: &HitRecord) -> Vec3d {
+        // Work with the outward normal, the maps describe the front of the surface
+        let side = if hit.front_face { 1.0 } else { -1.0 };
+        let n = side * hit.shading_normal;
+
+        let perturbed = match &self.map {
+            NormalMap::Normal { image, strength } => {
+                let tangent = hit.dpdu - hit.dpdu.dot(&n) * n;
+                if tangent.is_near_zero() {
+                    return hit.shading_normal;
+                }
+                let tangent = tangent.unit_vector();
+
+                // Mirrored texture coordinates flip the bitangent
+                let mut bitangent = n.cross(&tangent);
+                if bitangent.dot(&hit.dpdv) < 0.0 {
+                    bitangent = -bitangent;
+                }
+
+                let c = image.data_value(hit.u, hit.v);
+                strength * (2.0 * c.x - 1.0) * tangent
+                    + strength * (2.0 * c.y - 1.0) * bitangent
+                    + (2.0 * c.z - 1.0) * n
+            }
+            NormalMap::Bump { texture, scale } => {
+                let height = |du: f64, dv: f64| {
+                    let point = hit.point + du * hit.dpdu + dv * hit.dpdv;
+                    scale * color::luminance(&texture.value(hit.u + du, hit.v + dv, &point))
+                };
+                let h = height(0.0, 0.0);
+                let dhdu = (height(BUMP_DELTA, 0.0) - h) / BUMP_DELTA;
+                let dhdv = (height(0.0, BUMP_DELTA) - h) / BUMP_DELTA;
+
+                // Normal of the surface displaced by the height along the normal
+                let m = (hit.dpdu + dhdu * n).cross(&(hit.dpdv + dhdv * n));
+                if m.dot(&n) < 0.0 {
+                    -m
+                } else {
+                    m
+                }
+            }
+        };
+
+        if perturbed.is_near_zero() {
+            return hit.shading_normal;
+        }
+
+        side * perturbed.unit_vector()
+    }
+}
+
+impl Material for NormalMapped {
+    fn scatter(&self, rnd: &mut dyn FnMut(Range<f64>) -> f64, ray: &Ray, hit: &HitRecord) -> Option<ScatterResult> {
+        self.material.scatter(rnd, ray, &self.mapped(hit))
+    }
+
+    fn emitted(&self, hit: &HitRecord) -> Color {
+        self.material.emitted(hit)
+    }
+
+    fn eval(&self, ray: &Ray, hit: &HitRecord, direction: &Vec3d) -> Option<ScatterEval> {
+        self.material.eval(ray, &self.mapped(hit), direction)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{color, material::Lambertian, texture::Checker};
+    use approx::*;
+
+    fn mapped(map: NormalMap) -> NormalMapped {
+        NormalMapped {
+            material: Box::new(MaterialConfig::Lambertian(Lambertian {
+                albedo: Texture::Solid(color!(0.5, 0.5, 0.5)),
+            })),
+            map,
+        }
+    }
+
+    #[test]
+    fn test_bump() {
+        let flat = mapped(NormalMap::Bump {
+            texture: Texture::Solid(color!(0.5, 0.5, 0.5)),
+            scale: 1.0,
+        });
+        let material = MaterialConfig::NormalMapped(flat.clone());
+        assert_relative_eq!(flat.shading_normal(&HitRecord::on_xy_plane(&material, 0.2, 0.2)).z, 1.0);
+
+        // Stepping down from a white to a black square tilts the normal downhill
+        let step = mapped(NormalMap::Bump {
+            texture: Texture::Checker(Checker {
+                scale: 0.5,
+                even: Box::new(Texture::Solid(color!(1.0, 1.0, 1.0))),
+                odd: Box::new(Texture::Solid(color!(0.0, 0.0, 0.0))),
+                uv: true,
+            }),
+            scale: 1e-3,
+        });
+        let material = MaterialConfig::NormalMapped(step.clone());
+        let normal = step.shading_normal(&HitRecord::on_xy_plane(&material, 0.4995, 0.25));
+        assert!(normal.x > 0.5);
+        assert_relative_eq!(normal.y, 0.0);
+    }
+
+    #[test]
+    fn test_normal_map() {
+        let file_path = std::env::temp_dir().join("weekend-ray-test-normal-map.png");
+        image::RgbImage::from_pixel(1, 1, image::Rgb([255, 128, 255]))
+            .save(&file_path)
+            .unwrap();
+
+        let image = serde_json::from_value(serde_json::json!({ "file_path": file_path })).unwrap();
+        let normal_map = mapped(NormalMap::Normal { image, strength: 1.0 });
+        let material = MaterialConfig::NormalMapped(normal_map.clone());
+
+        // Tilted half way towards the u direction, also on the back of the surface
+        let mut hit = HitRecord::on_xy_plane(&material, 0.5, 0.5);
+        let normal = normal_map.shading_normal(&hit);
+        assert_relative_eq!(normal.x, 0.5f64.sqrt(), epsilon = 1e-2);
+        assert_relative_eq!(normal.z, 0.5f64.sqrt(), epsilon = 1e-2);
+
+        hit.front_face = false;
+        hit.normal = -hit.normal;
+        hit.shading_normal = -hit.shading_normal;
+        let normal = normal_map.shading_normal(&hit);
+        assert_relative_eq!(normal.x, -(0.5f64.sqrt()), epsilon = 1e-2);
+        assert_relative_eq!(normal.z, -(0.5f64.sqrt()), epsilon = 1e-2);
+    }
+}
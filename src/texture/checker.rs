@@ -0,0 +1,68 @@
+use crate::core::{Color, Vec3d};
+
+use super::Texture;
+
+/// Alternating squares of two textures, checkered in space or in texture coordinates
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+pub struct Checker {
+    /// Size of the squares, in scene units or in texture coordinates with `uv`
+    pub scale: f64,
+
+    pub even: Box<Texture>,
+    pub odd: Box<Texture>,
+
+    /// Alternate in the texture coordinates of the surface instead of in space
+    #[serde(default)]
+    pub uv: bool,
+}
+
+impl Checker {
+    pub fn value(&self, u: f64, v: f64, point: &Vec3d) -> Color {
+        let inv_scale = 1.0 / self.scale;
+        let sum = if self.uv {
+            (u * inv_scale).floor() + (v * inv_scale).floor()
+        } else {
+            (point.x * inv_scale).floor() + (point.y * inv_scale).floor() + (point.z * inv_scale).floor()
+        };
+
+        if sum as i64 % 2 == 0 {
+            self.even.value(u, v, point)
+        } else {
+            self.odd.value(u, v, point)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{color, v3d};
+
+    fn test_checker(uv: bool) -> Checker {
+        Checker {
+            scale: 0.5,
+            even: Box::new(Texture::Solid(color!(1.0, 1.0, 1.0))),
+            odd: Box::new(Texture::Solid(color!(0.0, 0.0, 0.0))),
+            uv,
+        }
+    }
+
+    #[test]
+    fn test_spatial() {
+        let checker = test_checker(false);
+
+        assert_eq!(checker.value(0.0, 0.0, &v3d!(0.1, 0.1, 0.1)).x, 1.0);
+        assert_eq!(checker.value(0.0, 0.0, &v3d!(0.6, 0.1, 0.1)).x, 0.0);
+        assert_eq!(checker.value(0.0, 0.0, &v3d!(-0.1, 0.1, 0.1)).x, 0.0);
+    }
+
+    #[test]
+    fn test_uv() {
+        let checker = test_checker(true);
+        let point = v3d!(0.0, 0.0, 0.0);
+
+        assert_eq!(checker.value(0.25, 0.25, &point).x, 1.0);
+        assert_eq!(checker.value(0.75, 0.25, &point).x, 0.0);
+        assert_eq!(checker.value(0.75, 0.75, &point).x, 1.0);
+    }
+}
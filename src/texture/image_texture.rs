@@ -0,0 +1,118 @@
+use std::sync::Arc;
+
+use crate::color;
+use crate::core::{Color, Vec3d};
+
+/// Colors from a PNG or JPEG image, mapped with the texture coordinates of the surface
+///
+/// The image is loaded while reading the scene file, so a missing or broken file is reported
+/// before rendering starts.
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+#[serde(try_from = "ImageTextureFile")]
+pub struct ImageTexture {
+    /// Path to a `.png` or `.jpg` image
+    pub file_path: String,
+
+    #[serde(skip)]
+    image: Arc<TextureImage>,
+}
+
+/// `ImageTexture` as written in the scene file
+#[derive(serde::Deserialize)]
+struct ImageTextureFile {
+    file_path: String,
+}
+
+/// Pixels as stored in the file, row by row from the top
+#[derive(Clone)]
+struct TextureImage {
+    width: usize,
+    height: usize,
+    pixels: Vec<[u8; 3]>,
+}
+
+impl TryFrom<ImageTextureFile> for ImageTexture {
+    type Error = String;
+
+    fn try_from(file: ImageTextureFile) -> Result<Self, String> {
+        Ok(ImageTexture {
+            image: Arc::new(TextureImage::load(&file.file_path)?),
+            file_path: file.file_path,
+        })
+    }
+}
+
+impl ImageTexture {
+    /// Nearest pixel for the texture coordinates as a linear color, `v` = 0 is the bottom of the image
+    pub fn value(&self, u: f64, v: f64) -> Color {
+        // Images are stored with gamma, undo it like the renderer applies it (gamma 2)
+        let [r, g, b] = self.image.pixel(u, v).map(|c| (c as f64 / 255.0).powi(2));
+        color!(r, g, b)
+    }
+
+    /// Pixel values as stored in the image, for data which isn't a gamma encoded color like normals
+    pub fn data_value(&self, u: f64, v: f64) -> Vec3d {
+        let [r, g, b] = self.image.pixel(u, v).map(|c| c as f64 / 255.0);
+        color!(r, g, b)
+    }
+}
+
+impl TextureImage {
+    fn load(file_path: &str) -> Result<Self, String> {
+        let img = image::open(file_path)
+            .map_err(|err| format!("Unable to read texture '{file_path}': {err}"))?
+            .into_rgb8();
+
+        Ok(TextureImage {
+            width: img.width() as usize,
+            height: img.height() as usize,
+            pixels: img.pixels().map(|p| p.0).collect(),
+        })
+    }
+
+    /// Nearest pixel for the texture coordinates
+    fn pixel(&self, u: f64, v: f64) -> [u8; 3] {
+        let x = ((u.clamp(0.0, 1.0) * self.width as f64) as usize).min(self.width - 1);
+        let y = (((1.0 - v.clamp(0.0, 1.0)) * self.height as f64) as usize).min(self.height - 1);
+
+        self.pixels[y * self.width + x]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::texture::Texture;
+
+    #[test]
+    fn test_value_flips_rows() {
+        let texture = ImageTexture {
+            file_path: String::new(),
+            image: Arc::new(TextureImage {
+                width: 2,
+                height: 2,
+                pixels: vec![[255, 0, 0], [0, 255, 0], [0, 0, 255], [255, 255, 128]],
+            }),
+        };
+
+        // The first row is the top of the image
+        assert_eq!(texture.value(0.25, 0.75).x, 1.0);
+        assert_eq!(texture.value(0.75, 0.75).y, 1.0);
+        assert_eq!(texture.value(0.25, 0.25).z, 1.0);
+        assert_eq!(texture.value(1.0, 0.0).x, 1.0);
+
+        // Data keeps the stored values, colors are linear
+        assert_eq!(texture.data_value(1.0, 0.0).z, 128.0 / 255.0);
+        assert_eq!(texture.value(1.0, 0.0).z, (128.0f64 / 255.0).powi(2));
+    }
+
+    #[test]
+    fn test_missing_file_fails_to_load() {
+        let result = serde_json::from_value::<Texture>(serde_json::json!({ "Image": { "file_path": "missing.png" } }));
+        assert!(result
+            .err()
+            .unwrap()
+            .to_string()
+            .contains("Unable to read texture 'missing.png'"));
+    }
+}
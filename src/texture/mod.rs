@@ -0,0 +1,90 @@
+mod checker;
+mod image_texture;
+mod noise;
+mod perlin;
+
+pub use checker::Checker;
+pub use image_texture::ImageTexture;
+pub use noise::Noise;
+
+use crate::core::{self, Color, TaggedOr, Vec3d};
+
+/// Color varying over a surface, e.g. the albedo of a material
+///
+/// A plain color in the scene file is a `Solid` texture, the other variants are tagged.
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+#[serde(remote = "Self")]
+pub enum Texture {
+    Checker(Checker),
+    Image(ImageTexture),
+    Noise(Noise),
+
+    /// Read and written without a tag
+    #[serde(skip_deserializing)]
+    Solid(Color),
+}
+
+impl<'de> TaggedOr<'de> for Texture {
+    type Untagged = Color;
+
+    fn deserialize_tagged<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Texture::deserialize(deserializer)
+    }
+}
+
+impl From<Color> for Texture {
+    fn from(color: Color) -> Self {
+        Texture::Solid(color)
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for Texture {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        core::deserialize_tagged_or(deserializer)
+    }
+}
+
+impl serde::Serialize for Texture {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Texture::Solid(color) => serde::Serialize::serialize(color, serializer),
+            _ => Texture::serialize(self, serializer),
+        }
+    }
+}
+
+impl Texture {
+    /// Color at the surface coordinates `u`, `v` of the hit `point`
+    #[inline]
+    pub fn value(&self, u: f64, v: f64, point: &Vec3d) -> Color {
+        match self {
+            Texture::Checker(t) => t.value(u, v, point),
+            Texture::Image(t) => t.value(u, v),
+            Texture::Noise(t) => t.value(point),
+            Texture::Solid(color) => *color,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color;
+
+    #[test]
+    fn test_solid_color_without_tag() {
+        let json = serde_json::to_value(Texture::Solid(color!(0.1, 0.2, 0.3))).unwrap();
+        assert_eq!(json, serde_json::json!({ "x": 0.1, "y": 0.2, "z": 0.3 }));
+
+        let texture: Texture = serde_json::from_value(json).unwrap();
+        assert!(matches!(texture, Texture::Solid(color) if color.y == 0.2));
+    }
+
+    #[test]
+    fn test_unknown_tag() {
+        let json = serde_json::json!({ "Marble": { "scale": 1.0 } });
+
+        let err = serde_json::from_value::<Texture>(json).err().unwrap().to_string();
+        assert!(err.starts_with("unknown variant `Marble`, expected one of `Checker`, `Image`, `Noise`"));
+    }
+}
@@ -0,0 +1,173 @@
+use std::sync::OnceLock;
+
+use super::perlin::Perlin;
+use crate::color;
+use crate::core::{Color, Vec3d};
+
+/// Procedural pattern from Perlin noise in space, mapped to colors with a ramp
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+pub struct Noise {
+    pub pattern: NoisePattern,
+
+    /// Frequency of the pattern, larger values give finer detail
+    pub scale: f64,
+
+    /// Number of noise layers summed for turbulence and fBm
+    #[serde(default = "default_octaves")]
+    pub octaves: u32,
+
+    /// How strongly noise distorts the marble stripes and wood rings, 10 for marble and 1 for wood by default
+    #[serde(default)]
+    pub distortion: Option<f64>,
+
+    #[serde(default)]
+    pub seed: u64,
+
+    /// Colors at increasing positions in [0, 1], interpolated linearly in between
+    #[serde(default = "default_ramp", deserialize_with = "deserialize_ramp")]
+    pub ramp: Vec<(f64, Color)>,
+
+    #[serde(skip)]
+    perlin: OnceLock<Perlin>,
+}
+
+#[derive(Clone, Copy, serde::Serialize, serde::Deserialize)]
+pub enum NoisePattern {
+    /// Summed absolute noise, cloudy with sharp creases
+    Turbulence,
+    /// Fractal Brownian motion, soft clouds
+    Fbm,
+    /// Stripes along z distorted by turbulence, a few per noise feature
+    Marble,
+    /// Rings around the y axis distorted by fBm, a few per noise feature
+    Wood,
+}
+
+/// Frequency of marble stripes and wood rings relative to the noise
+const STRIPES: f64 = 4.0;
+
+fn default_octaves() -> u32 {
+    7
+}
+
+fn default_ramp() -> Vec<(f64, Color)> {
+    vec![(0.0, color!(0.0, 0.0, 0.0)), (1.0, color!(1.0, 1.0, 1.0))]
+}
+
+/// Ramp read from the scene file, which must have at least one color
+///
+/// The positions must be in [0, 1] and must not decrease, equal ones give a hard step.
+fn deserialize_ramp<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Vec<(f64, Color)>, D::Error> {
+    let ramp = <Vec<(f64, Color)> as serde::Deserialize>::deserialize(deserializer)?;
+    if ramp.is_empty() {
+        return Err(serde::de::Error::custom(
+            "The color ramp of a noise texture needs at least one color",
+        ));
+    }
+
+    if let Some(&(position, _)) = ramp.iter().find(|(position, _)| !(0.0..=1.0).contains(position)) {
+        return Err(serde::de::Error::custom(format!(
+            "The color ramp position {position} of a noise texture is not in [0, 1]"
+        )));
+    }
+
+    if ramp.windows(2).any(|stops| stops[1].0 < stops[0].0) {
+        return Err(serde::de::Error::custom(
+            "The color ramp positions of a noise texture must be in ascending order",
+        ));
+    }
+
+    Ok(ramp)
+}
+
+impl Noise {
+    pub fn value(&self, point: &Vec3d) -> Color {
+        let perlin = self.perlin.get_or_init(|| Perlin::new(self.seed));
+        let p = self.scale * point;
+
+        let t = match self.pattern {
+            NoisePattern::Turbulence => perlin.turbulence(&p, self.octaves),
+            NoisePattern::Fbm => 0.5 * (1.0 + perlin.fbm(&p, self.octaves)),
+            NoisePattern::Marble => {
+                let distortion = self.distortion.unwrap_or(10.0);
+                0.5 * (1.0 + (STRIPES * p.z + distortion * perlin.turbulence(&p, self.octaves)).sin())
+            }
+            NoisePattern::Wood => {
+                let distortion = self.distortion.unwrap_or(1.0);
+                let rings = STRIPES * (p.x * p.x + p.z * p.z).sqrt() + distortion * perlin.fbm(&p, self.octaves);
+                rings - rings.floor()
+            }
+        };
+
+        self.ramp_color(t)
+    }
+
+    fn ramp_color(&self, t: f64) -> Color {
+        let Some(&(first, first_color)) = self.ramp.first() else {
+            panic!("The color ramp of a noise texture needs at least one color");
+        };
+        if t <= first {
+            return first_color;
+        }
+
+        for stops in self.ramp.windows(2) {
+            let ((start, start_color), (end, end_color)) = (stops[0], stops[1]);
+            if t <= end {
+                let s = if end > start { (t - start) / (end - start) } else { 1.0 };
+                return (1.0 - s) * start_color + s * end_color;
+            }
+        }
+
+        self.ramp.last().unwrap().1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::v3d;
+
+    #[test]
+    fn test_ramp() {
+        let noise = Noise {
+            pattern: NoisePattern::Fbm,
+            scale: 1.0,
+            octaves: default_octaves(),
+            distortion: None,
+            seed: 0,
+            ramp: vec![
+                (0.0, color!(1.0, 0.0, 0.0)),
+                (0.5, color!(0.0, 1.0, 0.0)),
+                (1.0, color!(0.0, 0.0, 1.0)),
+            ],
+            perlin: OnceLock::new(),
+        };
+
+        assert_eq!(noise.ramp_color(-1.0).x, 1.0);
+        assert_eq!(noise.ramp_color(0.25).x, 0.5);
+        assert_eq!(noise.ramp_color(0.25).y, 0.5);
+        assert_eq!(noise.ramp_color(0.75).z, 0.5);
+        assert_eq!(noise.ramp_color(1.5).z, 1.0);
+
+        // fBm is zero on the lattice, the middle of the ramp
+        assert_eq!(noise.value(&v3d!(1.0, 2.0, 3.0)).y, 1.0);
+    }
+
+    #[test]
+    fn test_invalid_ramp_is_rejected() {
+        let noise = |positions: &[f64]| {
+            let ramp: Vec<_> = positions
+                .iter()
+                .map(|position| serde_json::json!([position, { "x": 1.0, "y": 0.0, "z": 0.0 }]))
+                .collect();
+            serde_json::from_value::<Noise>(serde_json::json!({ "pattern": "Fbm", "scale": 1.0, "ramp": ramp }))
+        };
+
+        assert!(noise(&[]).is_err());
+        assert!(noise(&[0.0]).is_ok());
+        assert!(noise(&[0.0, 0.5, 0.5, 1.0]).is_ok());
+        assert!(noise(&[0.5, 0.2]).is_err());
+        assert!(noise(&[-0.1, 0.5]).is_err());
+        assert!(noise(&[0.5, 1.5]).is_err());
+    }
+}
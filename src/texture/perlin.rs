@@ -0,0 +1,113 @@
+use rand::{rngs::SmallRng, seq::SliceRandom, SeedableRng};
+
+use crate::core::Vec3d;
+
+const POINT_COUNT: usize = 256;
+
+/// Seeded gradient noise, the same seed always gives the same noise
+#[derive(Clone)]
+pub struct Perlin {
+    gradients: Vec<Vec3d>,
+    permutations: [Vec<u8>; 3],
+}
+
+impl Perlin {
+    pub fn new(seed: u64) -> Self {
+        let mut rng = SmallRng::seed_from_u64(seed);
+
+        let gradients = (0..POINT_COUNT).map(|_| Vec3d::random_unit_vector(&mut rng)).collect();
+        let permutations = [(); 3].map(|_| {
+            let mut permutation: Vec<u8> = (0..POINT_COUNT).map(|i| i as u8).collect();
+            permutation.shuffle(&mut rng);
+            permutation
+        });
+
+        Perlin {
+            gradients,
+            permutations,
+        }
+    }
+
+    /// Smooth noise in about [-1, 1], zero at every integer lattice point
+    pub fn noise(&self, point: &Vec3d) -> f64 {
+        let floor = [point.x.floor(), point.y.floor(), point.z.floor()];
+        let fraction = [point.x - floor[0], point.y - floor[1], point.z - floor[2]];
+        let cell = floor.map(|f| f as i64);
+
+        // Hermite smoothing of the interpolation weights hides the lattice
+        let [u, v, w] = fraction.map(|f| f * f * (3.0 - 2.0 * f));
+
+        let mut sum = 0.0;
+        for di in 0..2 {
+            for dj in 0..2 {
+                for dk in 0..2 {
+                    let index = self.permutations[0][((cell[0] + di) & 255) as usize]
+                        ^ self.permutations[1][((cell[1] + dj) & 255) as usize]
+                        ^ self.permutations[2][((cell[2] + dk) & 255) as usize];
+                    let offset = Vec3d {
+                        x: fraction[0] - di as f64,
+                        y: fraction[1] - dj as f64,
+                        z: fraction[2] - dk as f64,
+                    };
+
+                    let weight = |t: f64, d: i64| if d == 1 { t } else { 1.0 - t };
+                    sum += weight(u, di) * weight(v, dj) * weight(w, dk) * self.gradients[index as usize].dot(&offset);
+                }
+            }
+        }
+
+        sum
+    }
+
+    /// Sum of `octaves` noise layers, each with double the frequency and half the amplitude
+    pub fn fbm(&self, point: &Vec3d, octaves: u32) -> f64 {
+        self.octaves(point, octaves, |n| n)
+    }
+
+    /// Like `fbm` but summing the absolute noise, giving sharp creases where it changes sign
+    pub fn turbulence(&self, point: &Vec3d, octaves: u32) -> f64 {
+        self.octaves(point, octaves, f64::abs)
+    }
+
+    fn octaves(&self, point: &Vec3d, octaves: u32, f: impl Fn(f64) -> f64) -> f64 {
+        let mut sum = 0.0;
+        let mut point = *point;
+        let mut amplitude = 1.0;
+
+        for _ in 0..octaves {
+            sum += amplitude * f(self.noise(&point));
+            point *= 2.0;
+            amplitude *= 0.5;
+        }
+
+        sum
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::v3d;
+
+    #[test]
+    fn test_seeded() {
+        let point = v3d!(1.3, -2.7, 0.4);
+
+        assert_eq!(Perlin::new(7).noise(&point), Perlin::new(7).noise(&point));
+        assert_ne!(Perlin::new(7).noise(&point), Perlin::new(8).noise(&point));
+    }
+
+    #[test]
+    fn test_noise() {
+        let perlin = Perlin::new(0);
+
+        assert_eq!(perlin.noise(&v3d!(3.0, -5.0, 12.0)), 0.0);
+
+        for i in 0..1000 {
+            let i = i as f64;
+            let point = v3d!(i * 0.137, i * -0.071, i * 0.053);
+            assert!(perlin.noise(&point).abs() <= 1.0);
+            assert!(perlin.turbulence(&point, 5) >= 0.0);
+        }
+    }
+}